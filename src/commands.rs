@@ -0,0 +1,841 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::time::Duration;
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use rdb::rdb::address::VirtAddr;
+use rdb::rdb::disassembler::{disassemble, Syntax};
+use rdb::rdb::function_table::ReturnType;
+use rdb::rdb::process::{FollowForkMode, Process, ProcessState};
+use rdb::rdb::register_info::{Register, RegisterId, RegisterType, REGISTERS};
+use rdb::rdb::register_value::RegisterValue;
+use rdb::rdb::stop_reason::{StopInfo, StopReason, TrapKind};
+use rdb::rdb::stoppoint_collection::Stoppoint;
+use rdb::rdb::watchpoint::StoppointMode;
+use rdb::utils::attach::attach_by_name;
+use rdb::utils::format::{format_string, hexdump, DisplayFormat, ExamineFormat};
+use rdb::utils::parse::{parse_address, parse_u64, parse_vector};
+use rdb::utils::proc::ProcessEntry;
+use rdb::Error;
+
+/// Signalled by the thread forwarding the current inferior's pty once everything was copied.
+static TERMINAL_DRAINED: Mutex<Option<Receiver<()>>> = Mutex::new(None);
+/// Where `list` without arguments carries on: the file and the first line not shown yet.
+static LIST_POSITION: Mutex<Option<(PathBuf, u64)>> = Mutex::new(None);
+/// Source lines shown on each side of the line a stop is at.
+const STOP_CONTEXT_LINES: u64 = 3;
+/// Source lines `list` shows at a time.
+const LIST_LINES: u64 = 10;
+/// The most `memory read` and `x` read at once, the buffer for it is allocated up front.
+const MAX_READ_LENGTH: u64 = 0x10000;
+
+/// Runs one command against `process`, the inferior being debugged. `inferiors` holds the other
+/// processes we debug, left stopped at a fork with detach-on-fork off.
+pub fn dispatch_command(process: &mut Process, inferiors: &mut Vec<Process>, command: &str) {
+    let args: Vec<&str> = command.split_whitespace().collect();
+    let Some(&command) = args.first() else {
+        return;
+    };
+    if command == "x" || command.starts_with("x/") {
+        handle_examine_command(process, &args);
+    } else if "continue".starts_with(command) {
+        let result = process.resume().and_then(|()| process.wait_on_signal());
+        match result {
+            Ok(reason) => handle_stop(process, reason),
+            Err(e) => eprintln!("{}", e)
+        }
+    } else if command == "run" || command == "restart" {
+        handle_run_command(process);
+    } else if command == "detach" {
+        match process.detach() {
+            Ok(()) => println!("Detached from process {}", process.pid()),
+            Err(e) => eprintln!("{}", e)
+        }
+    } else if command == "kill" {
+        match process.kill() {
+            Ok(()) => println!("Process {} killed", process.pid()),
+            Err(e) => eprintln!("{}", e)
+        }
+    } else if command == "attach" {
+        handle_attach_command(process, &args);
+    } else if command == "set" {
+        handle_set_command(process, &args);
+    } else if command == "inferior" {
+        handle_inferior_command(process, inferiors, &args);
+    } else if command == "stepi" || command == "si" {
+        match process.step_instruction() {
+            Ok(reason) => handle_stop(process, reason),
+            Err(e) => eprintln!("{}", e)
+        }
+    } else if "step".starts_with(command) {
+        match process.step_in() {
+            Ok(reason) => handle_stop(process, reason),
+            Err(e) => eprintln!("{}", e)
+        }
+    } else if "next".starts_with(command) {
+        match process.step_over() {
+            Ok(reason) => handle_stop(process, reason),
+            Err(e) => eprintln!("{}", e)
+        }
+    } else if "finish".starts_with(command) {
+        handle_finish_command(process);
+    } else if "thread".starts_with(command) {
+        handle_thread_command(process, &args);
+    } else if "register".starts_with(command) {
+        handle_register_command(process, &args);
+    } else if "breakpoint".starts_with(command) {
+        handle_breakpoint_command(process, &args);
+    } else if "watchpoint".starts_with(command) {
+        handle_watchpoint_command(process, &args);
+    } else if "memory".starts_with(command) {
+        handle_memory_command(process, &args);
+    } else if "disassemble".starts_with(command) {
+        handle_disassemble_command(process, &args);
+    } else if "list".starts_with(command) {
+        handle_list_command(process, &args);
+    } else {
+        eprintln!("unknown command: {}", command)
+    }
+    if let Some(unfollowed) = process.take_unfollowed() {
+        println!("Process {} is kept stopped, switch to it with inferior {}", unfollowed.pid(), unfollowed.pid());
+        inferiors.push(unfollowed);
+    }
+}
+
+/// Runs until the current function returns and shows what it returned, inlined functions have
+/// no value to show.
+fn handle_finish_command(process: &mut Process) {
+    let function = process.function_frames().first().map(|f| (f.name.clone(), f.return_type, f.is_inlined()));
+    match &function {
+        Some((name, _, _)) => println!("Run till exit from {}", name),
+        None => println!("Run till exit from {}", format_address(process, process.get_pc())),
+    }
+    let reason = match process.step_out() {
+        Ok(reason) => reason,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    handle_stop(process, reason);
+    if reason.info != StopInfo::Trap(TrapKind::SingleStep) {
+        return;
+    }
+    // without debug information all we know is that integers come back in rax
+    let return_type = match function {
+        Some((_, _, true)) => return,
+        Some((_, return_type, false)) => return_type,
+        None => ReturnType::Other,
+    };
+    if let Some(value) = format_return_value(process, return_type) {
+        println!("Value returned: {}", value);
+    }
+}
+
+/// The value a function just returned according to the SysV ABI: integers and pointers in rax,
+/// floating point in xmm0. `None` for functions returning nothing.
+fn format_return_value(process: &Process, return_type: ReturnType) -> Option<String> {
+    let registers = process.registers();
+    let rax = registers.read_by_id_as::<u64>(RegisterId::Rax);
+    let value = match return_type {
+        ReturnType::Void => return None,
+        ReturnType::Bool => (rax & 0xff != 0).to_string(),
+        ReturnType::Integer { size, signed } => {
+            // the upper bits of rax are undefined for narrower types
+            let shift = 64 - 8 * size.clamp(1, 8);
+            match signed {
+                true => (((rax << shift) as i64) >> shift).to_string(),
+                false => ((rax << shift) >> shift).to_string(),
+            }
+        }
+        ReturnType::Pointer => format!("{:#x}", rax),
+        ReturnType::Float { size: 4 } => registers.read_by_id_as::<f32>(RegisterId::Xmm0).to_string(),
+        ReturnType::Float { size: 8 } => registers.read_by_id_as::<f64>(RegisterId::Xmm0).to_string(),
+        // long double comes back on the x87 stack
+        ReturnType::Float { .. } => format!("{} (st0)", registers.read_by_id(RegisterId::St0)),
+        ReturnType::Other => format!("{:#x} (rax)", rax),
+    };
+    Some(value)
+}
+
+/// Starts the program again with the options it was launched with, the old process is killed
+/// once the new one is up. Breakpoints and watchpoints are not carried over.
+fn handle_run_command(process: &mut Process) {
+    let Some(options) = process.launch_options().cloned() else {
+        eprintln!("Process {} was attached to, there is nothing to restart", process.pid());
+        return;
+    };
+    let result = Process::launch_with_options(&options).and_then(|mut relaunched| {
+        let reason = relaunched.wait_on_signal()?;
+        Ok((relaunched, reason))
+    });
+    match result {
+        Ok((mut relaunched, reason)) => {
+            carry_settings(process, &mut relaunched);
+            *process = relaunched;
+            forward_terminal(process);
+            warn_if_randomized(process);
+            handle_stop(process, reason);
+        }
+        Err(e) => eprintln!("{}", e)
+    }
+}
+
+/// `set <setting> [value]`, prints the current value without one. disable-randomization
+/// applies from the next `run`, the fork settings from the next fork.
+fn handle_set_command(process: &mut Process, args: &[&str]) {
+    let usage = "usage: set disable-randomization [on|off] | set follow-fork-mode [parent|child] | set detach-on-fork [on|off]";
+    let (setting, value) = match args {
+        [_, setting] => (*setting, None),
+        [_, setting, value] => (*setting, Some(*value)),
+        _ => {
+            eprintln!("{}", usage);
+            return;
+        }
+    };
+    let on_off = |value: &str| match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(Error::Parse(format!("Invalid value, expected on or off: {}", value))),
+    };
+    let show = |enabled: bool| if enabled { "on" } else { "off" };
+    let result = match setting {
+        "disable-randomization" => {
+            let Some(options) = process.launch_options_mut() else {
+                eprintln!("Process {} was attached to, it was not launched by us", process.pid());
+                return;
+            };
+            match value {
+                None => {
+                    let value = show(options.get_disable_randomization());
+                    println!("Disabling randomization of debuggee's virtual address space is {}.", value);
+                    Ok(())
+                }
+                Some(value) => on_off(value).map(|disable| *options = options.clone().disable_randomization(disable)),
+            }
+        }
+        "follow-fork-mode" => match value {
+            None => {
+                println!("Debugger response to a program call of fork or vfork is \"{}\".", process.follow_fork_mode().name());
+                Ok(())
+            }
+            Some(value) => FollowForkMode::parse(value).map(|mode| process.set_follow_fork_mode(mode)),
+        },
+        "detach-on-fork" => match value {
+            None => {
+                println!("Whether the debugger detaches from the side of a fork it does not follow is {}.", show(process.detach_on_fork()));
+                Ok(())
+            }
+            Some(value) => on_off(value).map(|detach| process.set_detach_on_fork(detach)),
+        },
+        _ => {
+            eprintln!("{}", usage);
+            return;
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
+
+/// `inferior [list]` shows the processes being debugged, `inferior <pid>` switches to one.
+fn handle_inferior_command(process: &mut Process, inferiors: &mut [Process], args: &[&str]) {
+    match args {
+        [_] | [_, "list"] => {
+            let current = std::iter::once(("*", &*process));
+            for (marker, inferior) in current.chain(inferiors.iter().map(|i| (" ", i))) {
+                let state = format!("{:?}", inferior.process_state).to_lowercase();
+                println!("{} process {}, {}", marker, inferior.pid(), state);
+            }
+        }
+        [_, pid] => {
+            let Some(inferior) = inferiors.iter_mut().find(|i| i.pid().to_string() == *pid) else {
+                eprintln!("No inferior with pid {}", pid);
+                return;
+            };
+            std::mem::swap(process, inferior);
+            println!("Switched to process {}", process.pid());
+            if let ProcessState::Stopped = process.process_state {
+                print_disassembly(process, process.get_pc(), 5, Syntax::Att);
+            }
+        }
+        _ => eprintln!("usage: inferior [list] | inferior <pid>")
+    }
+}
+
+/// A new process we debug in place of `old` keeps the fork settings made for it.
+fn carry_settings(old: &Process, new: &mut Process) {
+    new.set_follow_fork_mode(old.follow_fork_mode());
+    new.set_detach_on_fork(old.detach_on_fork());
+}
+
+/// Attaches to another process, the current one is killed if we launched it and detached from
+/// otherwise.
+fn handle_attach_command(process: &mut Process, args: &[&str]) {
+    let attached = match args {
+        [_, pid] => Process::attach(pid),
+        [_, "--name", name] => attach_by_name(name, false, choose_process),
+        [_, "--wait", name] => {
+            println!("Waiting for a process named {} to start", name);
+            attach_by_name(name, true, choose_process)
+        }
+        _ => {
+            eprintln!("usage: attach <pid> | attach --name <name> | attach --wait <name>");
+            return;
+        }
+    };
+    let result = attached.and_then(|mut attached| {
+        let reason = attached.wait_on_signal()?;
+        Ok((attached, reason))
+    });
+    match result {
+        Ok((mut attached, reason)) => {
+            carry_settings(process, &mut attached);
+            *process = attached;
+            forward_terminal(process);
+            handle_stop(process, reason);
+        }
+        Err(e) => eprintln!("{}", e)
+    }
+}
+
+fn handle_register_command(process: &mut Process, args: &[&str]) {
+    if args.len() < 2 {
+        eprintln!("usage: register read [all|<name>] | register write <name> <value>");
+        return;
+    }
+    if "read".starts_with(args[1]) {
+        match args.get(2) {
+            None => print_registers(process, |r| r.register_type == RegisterType::Gpr),
+            Some(&"all") => print_registers(process, |_| true),
+            Some(name) => match Register::by_name(name) {
+                Some(info) => println!("{}:\t{}", info.name, process.registers().read(info)),
+                None => eprintln!("No such register: {}", name)
+            }
+        }
+    } else if "write".starts_with(args[1]) && args.len() == 4 {
+        let Some(info) = Register::by_name(args[2]) else {
+            eprintln!("No such register: {}", args[2]);
+            return;
+        };
+        let written = RegisterValue::parse(info, args[3])
+            .and_then(|value| process.registers_mut().write(info, value));
+        if let Err(e) = written {
+            eprintln!("{}", e);
+        }
+    } else {
+        eprintln!("usage: register read [all|<name>] | register write <name> <value>");
+    }
+}
+
+fn handle_thread_command(process: &mut Process, args: &[&str]) {
+    let usage = "usage: thread list | thread select <id>";
+    match args {
+        [_, subcommand] if "list".starts_with(subcommand) => {
+            let current = process.current_thread().id();
+            for thread in process.threads() {
+                let marker = if thread.id() == current { "*" } else { " " };
+                let state = match thread.state() {
+                    ProcessState::Stopped => format!("stopped at {}", format_address(process, thread.get_pc())),
+                    state => format!("{:?}", state).to_lowercase(),
+                };
+                let detail = match thread.stop_reason().map(|r| r.info) {
+                    Some(StopInfo::Signal(signal)) => format!(" (signal {})", signal_name(signal)),
+                    Some(StopInfo::Trap(kind)) => trap_detail(process, kind),
+                    _ => String::new(),
+                };
+                println!("{} {}: tid {}, {}{}", marker, thread.id(), thread.tid(), state, detail);
+            }
+        }
+        [_, subcommand, id] if "select".starts_with(subcommand) => {
+            let Ok(id) = id.parse::<usize>() else {
+                eprintln!("Invalid thread id: {}", id);
+                return;
+            };
+            match process.select_thread(id) {
+                Ok(()) => {
+                    let thread = process.current_thread();
+                    println!("Thread {} (tid {}) at {}", thread.id(), thread.tid(), format_address(process, thread.get_pc()));
+                }
+                Err(e) => eprintln!("{}", e)
+            }
+        }
+        _ => eprintln!("{}", usage)
+    }
+}
+
+fn handle_breakpoint_command(process: &mut Process, args: &[&str]) {
+    let usage = "usage: breakpoint list | breakpoint set <address|symbol> [-h] | breakpoint enable|disable|delete <id>";
+    if args.len() < 2 {
+        eprintln!("{}", usage);
+        return;
+    }
+    let subcommand = args[1];
+    if "list".starts_with(subcommand) {
+        let mut sites = process.breakpoint_sites().iter().filter(|s| !s.is_internal()).peekable();
+        if sites.peek().is_none() {
+            println!("No breakpoints set");
+        }
+        for site in sites {
+            println!(
+                "{}: address = {}, {}{}",
+                site.id(), format_address(process, site.address()), if site.is_hardware() { "hardware, " } else { "" },
+                if site.is_enabled() { "enabled" } else { "disabled" }
+            );
+        }
+        return;
+    }
+    if "set".starts_with(subcommand) && (args.len() == 3 || args.len() == 4 && args[3] == "-h") {
+        let hardware = args.len() == 4;
+        // anything that does not parse as a number is taken for a symbol
+        let address = parse_address(args[2]).or_else(|_| process.lookup_symbol(args[2]));
+        let result = address.and_then(|address| {
+            let id = process.create_breakpoint_site(address, hardware)?;
+            if let Err(e) = process.enable_breakpoint_site(id) {
+                // an address we cannot patch is not worth keeping around as a disabled site
+                let _ = process.remove_breakpoint_site(id);
+                return Err(e);
+            }
+            Ok((id, address))
+        });
+        match result {
+            Ok((id, address)) => println!("Set breakpoint {} at {}", id, format_address(process, address)),
+            Err(e) => eprintln!("{}", e)
+        }
+        return;
+    }
+    if args.len() != 3 {
+        eprintln!("{}", usage);
+        return;
+    }
+    let Ok(id) = args[2].parse::<i32>() else {
+        eprintln!("Invalid breakpoint id: {}", args[2]);
+        return;
+    };
+    let result = if "enable".starts_with(subcommand) {
+        process.enable_breakpoint_site(id)
+    } else if "disable".starts_with(subcommand) {
+        process.disable_breakpoint_site(id)
+    } else if "delete".starts_with(subcommand) {
+        process.remove_breakpoint_site(id)
+    } else {
+        eprintln!("{}", usage);
+        return;
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
+
+fn handle_watchpoint_command(process: &mut Process, args: &[&str]) {
+    let usage = "usage: watchpoint list | watchpoint set <address> <write|rw|execute> <size> | watchpoint enable|disable|delete <id>";
+    if args.len() < 2 {
+        eprintln!("{}", usage);
+        return;
+    }
+    let subcommand = args[1];
+    if "list".starts_with(subcommand) {
+        if process.watchpoints().is_empty() {
+            println!("No watchpoints set");
+        }
+        for watchpoint in process.watchpoints().iter() {
+            println!(
+                "{}: address = {:#x}, mode = {}, size = {}, {}",
+                watchpoint.id(), watchpoint.address(), watchpoint.mode().name(), watchpoint.size(),
+                if watchpoint.is_enabled() { "enabled" } else { "disabled" }
+            );
+        }
+        return;
+    }
+    if "set".starts_with(subcommand) {
+        if args.len() != 5 {
+            eprintln!("{}", usage);
+            return;
+        }
+        let result = (|| {
+            let address = parse_address(args[2])?;
+            let mode = StoppointMode::parse(args[3])?;
+            let size = args[4].parse::<usize>().map_err(|_| Error::Parse(format!("Invalid size: {}", args[4])))?;
+            let id = process.create_watchpoint(address, mode, size)?;
+            if let Err(e) = process.enable_watchpoint(id) {
+                let _ = process.remove_watchpoint(id);
+                return Err(e);
+            }
+            Ok((id, address))
+        })();
+        match result {
+            Ok((id, address)) => println!("Set watchpoint {} at {:#x}", id, address),
+            Err(e) => eprintln!("{}", e)
+        }
+        return;
+    }
+    if args.len() != 3 {
+        eprintln!("{}", usage);
+        return;
+    }
+    let Ok(id) = args[2].parse::<i32>() else {
+        eprintln!("Invalid watchpoint id: {}", args[2]);
+        return;
+    };
+    let result = if "enable".starts_with(subcommand) {
+        process.enable_watchpoint(id)
+    } else if "disable".starts_with(subcommand) {
+        process.disable_watchpoint(id)
+    } else if "delete".starts_with(subcommand) {
+        process.remove_watchpoint(id)
+    } else {
+        eprintln!("{}", usage);
+        return;
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
+
+fn handle_memory_command(process: &mut Process, args: &[&str]) {
+    let usage = "usage: memory read <address> [length] | memory write <address> [0x01,0x02,...]";
+    if args.len() < 3 {
+        eprintln!("{}", usage);
+        return;
+    }
+    if "read".starts_with(args[1]) && args.len() <= 4 {
+        let result = (|| {
+            let address = parse_address(args[2])?;
+            let length = read_length(args.get(3).map_or(Ok(32), |length| parse_u64(length))?)?;
+            process.read_memory_without_traps(address, length).map(|bytes| (address, bytes))
+        })();
+        match result {
+            Ok((address, bytes)) => hexdump(address.addr(), &bytes).iter().for_each(|line| println!("{}", line)),
+            Err(e) => eprintln!("{}", e)
+        }
+    } else if "write".starts_with(args[1]) && args.len() >= 4 {
+        // allow spaces inside the vector, `[0x01, 0x02]`
+        let result = parse_address(args[2])
+            .and_then(|address| Ok((address, parse_vector(&args[3..].concat())?)))
+            .and_then(|(address, bytes)| process.write_memory(address, &bytes));
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    } else {
+        eprintln!("{}", usage);
+    }
+}
+
+/// `x/<count><unit><format> <address>`, as in gdb.
+fn handle_examine_command(process: &mut Process, args: &[&str]) {
+    if args.len() != 2 {
+        eprintln!("usage: x/<count><b|h|w|g><x|d|c|s|f> <address>");
+        return;
+    }
+    let result = (|| {
+        let format = ExamineFormat::parse(args[0].strip_prefix("x/").unwrap_or(""))?;
+        let length = read_length((format.count as u64).saturating_mul(format.unit_size as u64))?;
+        let address = parse_address(args[1])?;
+        if format.format == DisplayFormat::String {
+            let mut address = address;
+            let mut lines = Vec::new();
+            for _ in 0..format.count {
+                let string = read_string(process, address)?;
+                lines.push(format!("{:#018x}:\t{}", address, format_string(&string)));
+                address += string.len() as u64 + 1;
+            }
+            return Ok::<_, Error>(lines);
+        }
+        let bytes = process.read_memory_without_traps(address, length)?;
+        Ok(format.format_units(address.addr(), &bytes))
+    })();
+    match result {
+        Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+        Err(e) => eprintln!("{}", e)
+    }
+}
+
+/// `length` if it is no more than [`MAX_READ_LENGTH`].
+fn read_length(length: u64) -> Result<usize, Error> {
+    if length > MAX_READ_LENGTH {
+        return Err(Error::Parse(format!("Cannot read more than {:#x} bytes at once: {:#x}", MAX_READ_LENGTH, length)));
+    }
+    Ok(length as usize)
+}
+
+/// Reads a NUL terminated string, the NUL is not included.
+fn read_string(process: &Process, address: VirtAddr) -> Result<Vec<u8>, Error> {
+    // never read past a page boundary in one go, the next page may not be mapped even though
+    // the string ends before it
+    let mut string = Vec::new();
+    let mut current = address;
+    loop {
+        let chunk = (0x1000 - (current.addr() & 0xfff)).min(64) as usize;
+        let bytes = process.read_memory_without_traps(current, chunk)?;
+        if let Some(end) = bytes.iter().position(|&b| b == 0) {
+            string.extend_from_slice(&bytes[..end]);
+            return Ok(string);
+        }
+        string.extend_from_slice(&bytes);
+        current += chunk as u64;
+    }
+}
+
+fn handle_disassemble_command(process: &mut Process, args: &[&str]) {
+    let usage = "usage: disassemble [-c <count>] [-a <address>] [-s att|intel]";
+    let mut count = 5;
+    let mut address = process.get_pc();
+    let mut syntax = Syntax::Att;
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let Some(value) = options.next() else {
+            eprintln!("{}", usage);
+            return;
+        };
+        let parsed = match *option {
+            "-c" => parse_u64(value).map(|value| count = value as usize),
+            "-a" => parse_address(value).map(|value| address = value),
+            "-s" => Syntax::parse(value).map(|value| syntax = value),
+            _ => {
+                eprintln!("{}", usage);
+                return;
+            }
+        };
+        if let Err(e) = parsed {
+            eprintln!("{}", e);
+            return;
+        }
+    }
+    print_disassembly(process, address, count, syntax);
+}
+
+/// One instruction per line, `=>` marks the pc and `*` an enabled breakpoint site.
+fn print_disassembly(process: &Process, address: VirtAddr, count: usize, syntax: Syntax) {
+    let instructions = match disassemble(process, address, count, syntax) {
+        Ok(instructions) => instructions,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let pc = process.get_pc();
+    let locations: Vec<String> = instructions
+        .iter()
+        .map(|i| match process.symbolize(i.address) {
+            Some(location) => format!("{:#018x} <{}>:", i.address, location),
+            None => format!("{:#018x}:", i.address),
+        })
+        .collect();
+    let width = locations.iter().map(String::len).max().unwrap_or(0);
+    for (instruction, location) in instructions.iter().zip(locations) {
+        let bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let is_site = process.breakpoint_sites().enabled_stoppoint_at_address(instruction.address);
+        println!(
+            "{}{} {:<width$}  {:<30} {}",
+            if instruction.address == pc { "=>" } else { "  " }, if is_site { "*" } else { " " },
+            location, bytes.join(" "), instruction.text
+        );
+    }
+}
+
+/// Copies whatever the inferior writes to its pty onto our stdout until the pty goes away.
+/// This has to happen in the background, the program blocks once the pty buffer is full.
+pub fn forward_terminal(process: &Process) {
+    let Some(mut terminal) = process.terminal().and_then(|terminal| terminal.try_clone().ok()) else {
+        *TERMINAL_DRAINED.lock().unwrap() = None;
+        return;
+    };
+    let (drained, receiver) = mpsc::channel();
+    *TERMINAL_DRAINED.lock().unwrap() = Some(receiver);
+    std::thread::spawn(move || {
+        // ends with EIO once the program and everything it started have closed the pty
+        let _ = std::io::copy(&mut terminal, &mut std::io::stdout());
+        let _ = drained.send(());
+    });
+}
+
+/// Lists the processes and asks which one to use, `None` when the answer is not one of them.
+pub fn choose_process(candidates: &[ProcessEntry]) -> Option<Pid> {
+    println!("Several processes match:");
+    for (index, candidate) in candidates.iter().enumerate() {
+        println!("  [{}] {} {}", index + 1, candidate.pid, candidate.cmdline);
+    }
+    print!("Attach to which one? ");
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).ok()?;
+    let index = answer.trim().parse::<usize>().ok()?;
+    candidates.get(index.checked_sub(1)?).map(|candidate| candidate.pid)
+}
+
+/// Tells the user when the launch asked to disable randomization and the kernel refused, the
+/// program still runs.
+pub fn warn_if_randomized(process: &Process) {
+    if let Some(errno) = process.randomization_error() {
+        eprintln!("warning: Error disabling address space randomization: {}", errno.desc());
+    }
+}
+
+/// Reports a stop and, while the process is still around, shows the source line it stopped at,
+/// or the instructions when there is no source for it.
+pub fn handle_stop(process: &Process, reason: StopReason) {
+    if reason.state != ProcessState::Stopped
+        && let Some(drained) = TERMINAL_DRAINED.lock().unwrap().take() {
+        // let the program's last words come out before we say it is gone, something it left
+        // running may hold on to the pty though so do not wait forever
+        let _ = drained.recv_timeout(Duration::from_millis(100));
+    }
+    print_stop_reason(process, reason);
+    if reason.state != ProcessState::Stopped {
+        return;
+    }
+    let Some((file, line)) = process.source_location() else {
+        print_disassembly(process, process.get_pc(), 5, Syntax::Att);
+        return;
+    };
+    match process.function_frames().first().filter(|f| !f.name.is_empty()) {
+        Some(function) => println!("{} at {}:{}", function.name, file.file_name().unwrap_or_default().to_string_lossy(), line),
+        None => println!("{}:{}", file.file_name().unwrap_or_default().to_string_lossy(), line),
+    }
+    let first = line.saturating_sub(STOP_CONTEXT_LINES).max(1);
+    if let Err(e) = print_source(&file, first, line + STOP_CONTEXT_LINES, Some(line)) {
+        eprintln!("{}: {}", file.display(), e);
+        print_disassembly(process, process.get_pc(), 5, Syntax::Att);
+    }
+}
+
+/// `list` carries on with the next lines, `list 42` and `list hello.c:42` show the lines around
+/// that one.
+fn handle_list_command(process: &Process, args: &[&str]) {
+    let stopped_at = process.source_location();
+    let position = LIST_POSITION.lock().unwrap().clone()
+        .or_else(|| stopped_at.as_ref().map(|(file, line)| (file.to_path_buf(), line.saturating_sub(LIST_LINES / 2).max(1))));
+    let (file, first) = match args {
+        [_] => match position {
+            Some(position) => position,
+            None => {
+                eprintln!("No source to list");
+                return;
+            }
+        },
+        [_, location] => {
+            let (file, line) = match location.rsplit_once(':') {
+                Some((file, line)) => (find_source_file(process, file), line),
+                None => (position.map(|(file, _)| file), *location),
+            };
+            let Some(file) = file else {
+                eprintln!("No source file named {}", location.rsplit_once(':').map_or(*location, |(file, _)| file));
+                return;
+            };
+            let Ok(line) = line.parse::<u64>() else {
+                eprintln!("Invalid line number: {}", line);
+                return;
+            };
+            (file, line.saturating_sub(LIST_LINES / 2).max(1))
+        }
+        _ => {
+            eprintln!("usage: list [<line>|<file>:<line>]");
+            return;
+        }
+    };
+    let current = stopped_at.filter(|(stopped_file, _)| **stopped_file == *file).map(|(_, line)| line);
+    if let Err(e) = print_source(&file, first, first + LIST_LINES - 1, current) {
+        eprintln!("{}: {}", file.display(), e);
+    }
+}
+
+/// A file the line table knows of whose path ends in `name`, or `name` itself if it exists.
+fn find_source_file(process: &Process, name: &str) -> Option<PathBuf> {
+    let known = process.elf()
+        .and_then(|elf| elf.line_table())
+        .and_then(|table| table.entries().iter().find(|e| e.file.ends_with(name)))
+        .map(|e| e.file.to_path_buf());
+    known.or_else(|| Some(PathBuf::from(name)).filter(|path| path.is_file()))
+}
+
+/// Prints lines `first` to `last` of `file` as far as it goes, `>` marks `current`. The next
+/// `list` continues after them.
+fn print_source(file: &Path, first: u64, last: u64, current: Option<u64>) -> std::io::Result<()> {
+    let source = std::fs::read_to_string(file)?;
+    let count = source.lines().count() as u64;
+    if first > count {
+        println!("Line number {} out of range; \"{}\" has {} lines.", first, file.display(), count);
+        return Ok(());
+    }
+    for (number, text) in (1..).zip(source.lines()).skip(first as usize - 1).take((last + 1 - first) as usize) {
+        let marker = if Some(number) == current { ">" } else { " " };
+        println!("{} {:>4}\t{}", marker, number, text);
+    }
+    *LIST_POSITION.lock().unwrap() = Some((file.to_path_buf(), last.min(count) + 1));
+    Ok(())
+}
+
+/// Prints e.g. `Process 1234 stopped with signal TRAP at 0x401136 <main+0x4> (breakpoint 2)`,
+/// naming the thread that stopped when there is more than one.
+pub fn print_stop_reason(process: &Process, reason: StopReason) {
+    let pid = process.pid();
+    let stopped = match process.threads().len() {
+        1 => format!("Process {}", pid),
+        _ => format!("Process {} thread {}", pid, process.current_thread().id()),
+    };
+    match reason.info {
+        StopInfo::ExitCode(code) => println!("Process {} exited with status {}", pid, code),
+        StopInfo::Signal(signal) if reason.state == ProcessState::Terminated => {
+            println!("Process {} terminated with signal {}", pid, signal_name(signal));
+        }
+        StopInfo::Signal(signal) => {
+            println!("{} stopped with signal {} at {}", stopped, signal_name(signal), format_address(process, process.get_pc()));
+        }
+        StopInfo::Trap(TrapKind::Interrupt) => {
+            println!("{} interrupted at {}", stopped, format_address(process, process.get_pc()));
+        }
+        StopInfo::Trap(kind) => {
+            println!(
+                "{} stopped with signal TRAP at {}{}",
+                stopped, format_address(process, process.get_pc()), trap_detail(process, kind)
+            );
+        }
+    }
+}
+
+/// `0x401136 <main+0x4>`, just the address outside the executable's symbols.
+fn format_address(process: &Process, address: VirtAddr) -> String {
+    match process.symbolize(address) {
+        Some(location) => format!("{:#x} <{}>", address, location),
+        None => format!("{:#x}", address),
+    }
+}
+
+/// ` (breakpoint 2)`, empty for traps there is nothing more to say about.
+fn trap_detail(process: &Process, kind: TrapKind) -> String {
+    match kind {
+        TrapKind::SoftwareBreakpoint(id) => format!(" (breakpoint {})", id),
+        TrapKind::HardwareBreakpoint(id) => format!(" (hardware breakpoint {})", id),
+        TrapKind::Watchpoint { id, old_value, new_value } => {
+            format!(" (watchpoint {}, old value {:#x}, new value {:#x})", id, old_value, new_value)
+        }
+        TrapKind::SingleStep => " (single step)".to_string(),
+        TrapKind::Syscall => " (syscall)".to_string(),
+        TrapKind::Clone => " (clone)".to_string(),
+        TrapKind::Exec => match std::fs::read_link(format!("/proc/{}/exe", process.pid())) {
+            Ok(path) => format!(" (exec of {})", path.display()),
+            Err(_) => " (exec)".to_string(),
+        },
+        TrapKind::Fork => " (fork)".to_string(),
+        TrapKind::Vfork => " (vfork)".to_string(),
+        TrapKind::Interrupt => " (interrupt)".to_string(),
+        TrapKind::Unknown => String::new(),
+    }
+}
+
+fn print_registers(process: &Process, filter: impl Fn(&Register) -> bool) {
+    for info in REGISTERS.iter().filter(|r| filter(r)) {
+        // orig_rax is bookkeeping for syscall restarts rather than a real register
+        if info.name == "orig_rax" {
+            continue;
+        }
+        println!("{}:\t{}", info.name, process.registers().read(info));
+    }
+}
+
+/// `SIGTRAP` -> `TRAP`
+fn signal_name(signal: Signal) -> &'static str {
+    signal.as_str().trim_start_matches("SIG")
+}
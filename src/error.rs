@@ -0,0 +1,111 @@
+use std::fmt;
+use std::path::PathBuf;
+use nix::errno::Errno;
+use nix::unistd::Pid;
+use crate::rdb::address::VirtAddr;
+use crate::rdb::attach_diagnosis::AttachDenial;
+use crate::rdb::process::ProcessState;
+
+/// Everything that can go wrong inside the library. The library never prints or exits on its
+/// own, callers decide how to report these.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The pid given to attach was not a positive number.
+    InvalidPid(String),
+    /// Attaching to `pid` was refused for a reason we could not pin down.
+    Attach { pid: i32, errno: Errno },
+    /// Attaching to `pid` was refused and `/proc` tells us why.
+    AttachDenied { pid: i32, reason: AttachDenial },
+    /// The forked child could not start tracing or exec the program.
+    Launch { errno: Errno },
+    /// A ptrace request failed, `op` names the request or what it was for.
+    Ptrace { op: &'static str, errno: Errno },
+    /// Any other system call failed (fork, pipe, waitpid, kill, ...).
+    Os { op: &'static str, errno: Errno },
+    /// The process is gone, e.g. it already exited or was reaped.
+    NoSuchProcess(i32),
+    /// No process goes by this name.
+    ProcessNotFound(String),
+    /// No thread has this id.
+    NoSuchThread(usize),
+    /// No function or object of that name in the executable.
+    NoSuchSymbol(String),
+    /// The operation makes no sense in the state the process is in, e.g. killing it twice.
+    InvalidState { pid: i32, state: ProcessState },
+    /// Unknown register name, or a value the register cannot hold.
+    InvalidRegister(String),
+    /// Reading or writing inferior memory at `addr` failed.
+    MemoryAccess { addr: VirtAddr },
+    /// Breakpoint and watchpoint bookkeeping: unknown ids, duplicates, no free debug registers.
+    Stoppoint(String),
+    /// A file a standard stream of the inferior was redirected to could not be opened.
+    Redirect { path: PathBuf, errno: Errno },
+    /// Text that could not be turned into a number, vector or option.
+    Parse(String),
+    /// An executable could not be opened or mapped, or is not a 64-bit little endian ELF file.
+    Elf { path: PathBuf, message: String },
+    /// No call frame information says where the function running at this address returns to.
+    NoFrameInfo(VirtAddr),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Wraps a failed ptrace request, ESRCH means the tracee is gone (or not stopped).
+    pub(crate) fn ptrace(op: &'static str, pid: Pid) -> impl FnOnce(Errno) -> Error {
+        move |errno| match errno {
+            Errno::ESRCH => Error::NoSuchProcess(pid.as_raw()),
+            errno => Error::Ptrace { op, errno },
+        }
+    }
+
+    /// Wraps a failed PEEKDATA/POKEDATA at `addr`.
+    pub(crate) fn memory(addr: VirtAddr, pid: Pid) -> impl FnOnce(Errno) -> Error {
+        move |errno| match errno {
+            Errno::ESRCH => Error::NoSuchProcess(pid.as_raw()),
+            _ => Error::MemoryAccess { addr },
+        }
+    }
+
+    pub(crate) fn os(op: &'static str) -> impl FnOnce(Errno) -> Error {
+        move |errno| Error::Os { op, errno }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidPid(pid) => write!(f, "Invalid PID: {}", pid),
+            Error::Attach { pid, errno } => write!(f, "Failed to attach to {}: {}", pid, errno),
+            Error::AttachDenied { pid, reason } => write!(f, "Cannot attach to {}: {}", pid, reason),
+            Error::Launch { errno } => write!(f, "Failed to launch process: {}", errno),
+            Error::Ptrace { op, errno } => write!(f, "{} failed: {}", op, errno),
+            Error::Os { op, errno } => write!(f, "{} failed: {}", op, errno),
+            Error::NoSuchProcess(pid) => write!(f, "No such process: {}", pid),
+            Error::NoSuchThread(id) => write!(f, "No thread with id {}", id),
+            Error::NoSuchSymbol(name) => write!(f, "No symbol named {}", name),
+            Error::ProcessNotFound(name) => write!(f, "No process named {}", name),
+            Error::InvalidState { pid, state } => {
+                let state = match state {
+                    ProcessState::Stopped => "stopped",
+                    ProcessState::Running => "running",
+                    ProcessState::Exited => "exited",
+                    ProcessState::Terminated => "terminated",
+                    ProcessState::Detached => "detached",
+                };
+                write!(f, "Process {} is {}", pid, state)
+            }
+            Error::InvalidRegister(message) => write!(f, "{}", message),
+            Error::MemoryAccess { addr } => write!(f, "Could not access memory at {:#x}", addr),
+            Error::Stoppoint(message) => write!(f, "{}", message),
+            Error::Redirect { path, errno } => write!(f, "Could not open {}: {}", path.display(), errno),
+            Error::Parse(message) => write!(f, "{}", message),
+            Error::Elf { path, message } => write!(f, "{}: {}", path.display(), message),
+            Error::NoFrameInfo(pc) => {
+                write!(f, "No call frame information for {:#x}, cannot tell where the function returns to", pc)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,5 +1,8 @@
+pub mod error;
 pub mod utils;
 pub mod rdb;
 
+pub use error::Error;
+
 #[cfg(test)]
 mod unit_tests;
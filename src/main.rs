@@ -1,5 +1,6 @@
+mod commands;
+
 use std::{env, process};
-use nix::sys::wait::{waitpid, WaitStatus};
 use rustyline::DefaultEditor;
 use rustyline::error::ReadlineError;
 use rdb::rdb::process::{Process, ProcessState};
@@ -11,7 +12,7 @@ fn main() {
         eprintln!("give a process/binary path id to attach to");
         process::exit(1);
     }
-    let process:Result<Process, String> = attach(args);
+    let process = attach(args, commands::choose_process);
     let mut process = match process {
         Ok(p) => {p}
         Err(e) => {
@@ -19,38 +20,39 @@ fn main() {
             process::exit(1);
         }
     };
-    let wait_res = waitpid(process.pid(), None);
+    commands::forward_terminal(&process);
+    commands::warn_if_randomized(&process);
+    let wait_res = process.wait_on_signal();
     match wait_res {
-        Ok(WaitStatus::Stopped(child_pid, signal)) => {
-            println!("Process {} stopped by signal {:?}", child_pid, signal);
-            process.process_state = ProcessState::Stopped;
+        Ok(reason) if reason.state == ProcessState::Stopped => {
+            commands::handle_stop(&process, reason);
             debug(process);
         }
-        Ok(status) => {
-            eprintln!("Unexpected Status: {:?}", status);
-            process.process_state = ProcessState::Exited;
+        Ok(reason) => {
+            commands::handle_stop(&process, reason);
             process::exit(1);
         }
         Err(e) => {
-            eprintln!("Wait Pid failed: {}", e);
-            process.process_state = ProcessState::Terminated;
+            eprintln!("Error: {}", e);
             process::exit(1);
         }
     }
 }
 
 fn debug(mut process: Process) {
+    // processes left stopped at a fork, they are killed or detached from along with `process`
+    let mut inferiors = Vec::new();
     let mut rl = DefaultEditor::new().unwrap();
-    if let Err(e) = rl.load_history(".history"){
+    if rl.load_history(".history").is_err() {
         println!("No previous history.");
     }
     loop {
         let readline = rl.readline("rdb>> ");
         match readline {
             Ok(line) => {
-                if line != "" {
+                if !line.is_empty() {
                     let _ = rl.add_history_entry(line.as_str());
-                    process.dispatch_command(line);
+                    commands::dispatch_command(&mut process, &mut inferiors, &line);
                     // we want to handle command formats similar to GDB
                     // so to continue a program a user can say just continue, cont or c
                     // to set a breakpoint on an address >> break set 0xabcdabcd
@@ -65,6 +67,7 @@ fn debug(mut process: Process) {
                 // CTRL + d
                 println!("Exiting Debugger - CTRL + D");
                 drop(process);
+                drop(inferiors);
                 break;
             },
             Err(e) => {
@@ -0,0 +1,202 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use nix::libc;
+use crate::rdb::elf::Elf;
+
+/// An address in the inferior's address space, what the registers, memory and breakpoints deal
+/// in. Converting to and from link time addresses goes through the load bias of the object the
+/// address belongs to, see [`VirtAddr::to_file_addr`] and [`FileAddr::to_virt_addr`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VirtAddr(u64);
+
+impl VirtAddr {
+    pub const fn new(addr: u64) -> Self {
+        Self(addr)
+    }
+
+    pub const fn addr(self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(self, offset: u64) -> Option<Self> {
+        self.0.checked_add(offset).map(Self)
+    }
+
+    pub fn wrapping_sub(self, offset: u64) -> Self {
+        Self(self.0.wrapping_sub(offset))
+    }
+
+    /// The link time address in `elf`, `None` when no loaded segment of `elf` covers the address.
+    pub fn to_file_addr(self, elf: &Elf) -> Option<FileAddr<'_>> {
+        let addr = self.0.wrapping_sub(elf.load_bias());
+        elf.load_segment_containing(addr)?;
+        Some(FileAddr::new(elf, addr))
+    }
+}
+
+impl fmt::Display for VirtAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+impl fmt::LowerHex for VirtAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl Add<u64> for VirtAddr {
+    type Output = Self;
+
+    fn add(self, offset: u64) -> Self {
+        Self(self.0 + offset)
+    }
+}
+
+impl AddAssign<u64> for VirtAddr {
+    fn add_assign(&mut self, offset: u64) {
+        self.0 += offset;
+    }
+}
+
+impl Sub<u64> for VirtAddr {
+    type Output = Self;
+
+    fn sub(self, offset: u64) -> Self {
+        Self(self.0 - offset)
+    }
+}
+
+impl SubAssign<u64> for VirtAddr {
+    fn sub_assign(&mut self, offset: u64) {
+        self.0 -= offset;
+    }
+}
+
+/// How far apart two addresses are.
+impl Sub for VirtAddr {
+    type Output = u64;
+
+    fn sub(self, other: Self) -> u64 {
+        self.0 - other.0
+    }
+}
+
+/// A link time address, the `st_value`, `sh_addr` and `p_vaddr` kind, of one particular ELF
+/// object. Only addresses of the same object compare.
+#[derive(Clone, Copy)]
+pub struct FileAddr<'a> {
+    elf: &'a Elf,
+    addr: u64,
+}
+
+impl<'a> FileAddr<'a> {
+    pub fn new(elf: &'a Elf, addr: u64) -> Self {
+        Self { elf, addr }
+    }
+
+    pub fn elf(self) -> &'a Elf {
+        self.elf
+    }
+
+    pub fn addr(self) -> u64 {
+        self.addr
+    }
+
+    /// Where the object's load bias puts the address in the process.
+    pub fn to_virt_addr(self) -> VirtAddr {
+        VirtAddr(self.addr.wrapping_add(self.elf.load_bias()))
+    }
+
+    /// Where the address is in the file, `None` for addresses with no file contents behind
+    /// them such as `.bss`.
+    pub fn to_file_offset(self) -> Option<FileOffset<'a>> {
+        let segment = self.elf.load_segment_containing(self.addr)?;
+        let offset = self.addr - segment.p_vaddr;
+        (offset < segment.p_filesz).then(|| FileOffset::new(self.elf, segment.p_offset + offset))
+    }
+}
+
+impl fmt::Debug for FileAddr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FileAddr({:#x} in {})", self.addr, self.elf.path().display())
+    }
+}
+
+impl PartialEq for FileAddr<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.elf, other.elf) && self.addr == other.addr
+    }
+}
+
+impl Eq for FileAddr<'_> {}
+
+impl PartialOrd for FileAddr<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        std::ptr::eq(self.elf, other.elf).then(|| self.addr.cmp(&other.addr))
+    }
+}
+
+impl Add<u64> for FileAddr<'_> {
+    type Output = Self;
+
+    fn add(self, offset: u64) -> Self {
+        Self { addr: self.addr + offset, ..self }
+    }
+}
+
+impl Sub<u64> for FileAddr<'_> {
+    type Output = Self;
+
+    fn sub(self, offset: u64) -> Self {
+        Self { addr: self.addr - offset, ..self }
+    }
+}
+
+/// A position in the file of one particular ELF object.
+#[derive(Clone, Copy)]
+pub struct FileOffset<'a> {
+    elf: &'a Elf,
+    off: u64,
+}
+
+impl<'a> FileOffset<'a> {
+    pub fn new(elf: &'a Elf, off: u64) -> Self {
+        Self { elf, off }
+    }
+
+    pub fn elf(self) -> &'a Elf {
+        self.elf
+    }
+
+    pub fn off(self) -> u64 {
+        self.off
+    }
+
+    /// The link time address the loaded segment holding the offset gives it, `None` for parts
+    /// of the file that are not loaded.
+    pub fn to_file_addr(self) -> Option<FileAddr<'a>> {
+        self.elf
+            .program_headers()
+            .iter()
+            .filter(|p| p.p_type == libc::PT_LOAD)
+            .find(|p| (p.p_offset..p.p_offset + p.p_filesz).contains(&self.off))
+            .map(|p| FileAddr::new(self.elf, p.p_vaddr + (self.off - p.p_offset)))
+    }
+}
+
+impl fmt::Debug for FileOffset<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FileOffset({:#x} in {})", self.off, self.elf.path().display())
+    }
+}
+
+impl PartialEq for FileOffset<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.elf, other.elf) && self.off == other.off
+    }
+}
+
+impl Eq for FileOffset<'_> {}
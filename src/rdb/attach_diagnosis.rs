@@ -0,0 +1,174 @@
+use std::fmt;
+use nix::errno::Errno;
+use nix::unistd::{getpid, Pid};
+use crate::error::Error;
+
+/// Bit of CAP_SYS_PTRACE in the capability masks of `/proc/pid/status`.
+const CAP_SYS_PTRACE: u32 = 19;
+
+/// Why the kernel would not let us attach, as far as we can tell from `/proc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachDenial {
+    /// The process exited and waits to be reaped, there is nothing left to debug.
+    Zombie,
+    /// Another debugger (or strace, or a sandbox) traces it already, a process has one tracer.
+    AlreadyTraced { tracer: i32, tracer_name: String },
+    /// It runs as another user and we lack CAP_SYS_PTRACE.
+    DifferentUser { ours: u32, theirs: u32 },
+    /// Yama's `kernel.yama.ptrace_scope` forbids it. At scope 1 this is a guess, whether the
+    /// process allowed us with `PR_SET_PTRACER` cannot be read from `/proc`.
+    PtraceScope(u8),
+}
+
+impl fmt::Display for AttachDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttachDenial::Zombie => {
+                write!(f, "it is a zombie, it already exited and only waits for its parent to reap it")
+            }
+            AttachDenial::AlreadyTraced { tracer, tracer_name } => write!(
+                f,
+                "it is already traced by process {} ({}), detach that first, a process can only have one tracer",
+                tracer, tracer_name
+            ),
+            AttachDenial::DifferentUser { ours, theirs } => write!(
+                f,
+                "it runs as uid {} and we run as uid {} without CAP_SYS_PTRACE, run rdb as that user or with sudo",
+                theirs, ours
+            ),
+            AttachDenial::PtraceScope(1) => write!(
+                f,
+                "likely kernel.yama.ptrace_scope, which is 1 and lets only descendants be traced unless the \
+                 program named a tracer with PR_SET_PTRACER or the tracer has CAP_SYS_PTRACE. Launch the program \
+                 from rdb, run rdb with sudo, or allow it with `sudo sysctl kernel.yama.ptrace_scope=0`"
+            ),
+            AttachDenial::PtraceScope(2) => write!(
+                f,
+                "kernel.yama.ptrace_scope is 2, only CAP_SYS_PTRACE may attach. Run rdb with sudo"
+            ),
+            AttachDenial::PtraceScope(scope) => write!(
+                f,
+                "kernel.yama.ptrace_scope is {}, attaching is disabled until the next reboot",
+                scope
+            ),
+        }
+    }
+}
+
+/// What `/proc` says about us and the process we failed to attach to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct AttachFacts {
+    pub exists: bool,
+    /// The state letter from `/proc/pid/stat`.
+    pub state: Option<char>,
+    pub tracer: Option<(i32, String)>,
+    /// Our real uid, see [`caller_uid`].
+    pub our_uid: Option<u32>,
+    /// Real, effective and saved uid, all of which have to match ours.
+    pub their_uids: Vec<u32>,
+    pub has_cap_sys_ptrace: bool,
+    /// `None` without Yama.
+    pub ptrace_scope: Option<u8>,
+    pub is_descendant: bool,
+}
+
+/// Turns a failed PTRACE_SEIZE on `pid` into the most specific error we can find.
+pub(crate) fn diagnose(pid: i32, errno: Errno) -> Error {
+    if errno != Errno::EPERM && errno != Errno::ESRCH {
+        return Error::Attach { pid, errno };
+    }
+    explain(pid, errno, &AttachFacts::gather(Pid::from_raw(pid)))
+}
+
+/// Checks go from the most to the least certain, anything we cannot explain stays a plain
+/// [`Error::Attach`].
+pub(crate) fn explain(pid: i32, errno: Errno, facts: &AttachFacts) -> Error {
+    let denied = |reason| Error::AttachDenied { pid, reason };
+    if !facts.exists {
+        return Error::NoSuchProcess(pid);
+    }
+    if facts.state == Some('Z') {
+        return denied(AttachDenial::Zombie);
+    }
+    if let Some((tracer, tracer_name)) = &facts.tracer {
+        return denied(AttachDenial::AlreadyTraced { tracer: *tracer, tracer_name: tracer_name.clone() });
+    }
+    if let Some(ours) = facts.our_uid
+        && !facts.has_cap_sys_ptrace
+        && let Some(theirs) = facts.their_uids.iter().find(|uid| **uid != ours) {
+        return denied(AttachDenial::DifferentUser { ours, theirs: *theirs });
+    }
+    match facts.ptrace_scope {
+        Some(scope @ 3..) => denied(AttachDenial::PtraceScope(scope)),
+        Some(2) if !facts.has_cap_sys_ptrace => denied(AttachDenial::PtraceScope(2)),
+        Some(1) if !facts.has_cap_sys_ptrace && !facts.is_descendant => denied(AttachDenial::PtraceScope(1)),
+        _ => Error::Attach { pid, errno },
+    }
+}
+
+impl AttachFacts {
+    fn gather(pid: Pid) -> Self {
+        let Ok(status) = std::fs::read_to_string(format!("/proc/{}/status", pid.as_raw())) else {
+            return Self::default();
+        };
+        let ours = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+        let tracer = status_field(&status, "TracerPid")
+            .and_then(|tracer| tracer.parse::<i32>().ok())
+            .filter(|tracer| *tracer != 0)
+            .map(|tracer| (tracer, command_name(Pid::from_raw(tracer))));
+        let capabilities = status_field(&ours, "CapEff").and_then(|caps| u64::from_str_radix(caps, 16).ok());
+        Self {
+            exists: true,
+            state: status_field(&status, "State").and_then(|state| state.chars().next()),
+            tracer,
+            our_uid: caller_uid(&ours),
+            their_uids: uids(&status).into_iter().take(3).collect(),
+            has_cap_sys_ptrace: capabilities.is_some_and(|caps| caps & (1 << CAP_SYS_PTRACE) != 0),
+            ptrace_scope: std::fs::read_to_string("/proc/sys/kernel/yama/ptrace_scope")
+                .ok()
+                .and_then(|scope| scope.trim().parse().ok()),
+            is_descendant: is_descendant(pid),
+        }
+    }
+}
+
+fn status_field<'a>(status: &'a str, name: &str) -> Option<&'a str> {
+    status.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix(':')).map(str::trim)
+}
+
+/// The `Uid:` line lists real, effective, saved and filesystem uid.
+fn uids(status: &str) -> Vec<u32> {
+    status_field(status, "Uid")
+        .map(|uids| uids.split_whitespace().filter_map(|uid| uid.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// The uid ptrace compares with the target's from our `/proc/self/status`, which is the real
+/// one. A setuid rdb has a different effective uid.
+pub(crate) fn caller_uid(status: &str) -> Option<u32> {
+    uids(status).first().copied()
+}
+
+fn command_name(pid: Pid) -> String {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid.as_raw()))
+        .map(|name| name.trim_end().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Follows the parent chain up to init looking for us.
+fn is_descendant(pid: Pid) -> bool {
+    let mut current = pid.as_raw();
+    while current > 1 {
+        let Ok(status) = std::fs::read_to_string(format!("/proc/{}/status", current)) else {
+            return false;
+        };
+        let Some(parent) = status_field(&status, "PPid").and_then(|parent| parent.parse::<i32>().ok()) else {
+            return false;
+        };
+        if parent == getpid().as_raw() {
+            return true;
+        }
+        current = parent;
+    }
+    false
+}
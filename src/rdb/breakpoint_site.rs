@@ -0,0 +1,116 @@
+use nix::sys::ptrace;
+use nix::unistd::Pid;
+use crate::error::{Error, Result};
+use crate::rdb::address::VirtAddr;
+use crate::rdb::stoppoint_collection::Stoppoint;
+
+pub(crate) const INT3: u8 = 0xcc;
+
+/// A breakpoint at a single address.
+///
+/// Software sites swap the first byte of the instruction at `address` for `int3` while they are
+/// enabled and put it back when they are disabled. Hardware sites leave memory alone and occupy
+/// one of the four debug address registers instead, those are programmed by `Process` because
+/// they live in the register file.
+#[derive(Clone)]
+pub struct BreakpointSite {
+    id: i32,
+    pid: Pid,
+    address: VirtAddr,
+    is_enabled: bool,
+    saved_data: u8,
+    is_hardware: bool,
+    hardware_slot: Option<usize>,
+    is_internal: bool,
+}
+
+impl Stoppoint for BreakpointSite {
+    fn id(&self) -> i32 {
+        self.id
+    }
+    fn address(&self) -> VirtAddr {
+        self.address
+    }
+    fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+}
+
+impl BreakpointSite {
+    pub(crate) fn new(id: i32, pid: Pid, address: VirtAddr, is_hardware: bool, is_internal: bool) -> Self {
+        Self { id, pid, address, is_enabled: false, saved_data: 0, is_hardware, hardware_slot: None, is_internal }
+    }
+
+    pub fn is_hardware(&self) -> bool {
+        self.is_hardware
+    }
+
+    /// Set by rdb itself rather than the user, e.g. to run a fresh process up to `main`.
+    pub fn is_internal(&self) -> bool {
+        self.is_internal
+    }
+
+    /// The debug register (0-3) a hardware site occupies while enabled.
+    pub fn hardware_slot(&self) -> Option<usize> {
+        self.hardware_slot
+    }
+
+    pub(crate) fn set_hardware_slot(&mut self, slot: Option<usize>) {
+        self.hardware_slot = slot;
+        self.is_enabled = slot.is_some();
+    }
+
+    /// The byte `int3` replaced, only meaningful while the site is enabled.
+    pub fn saved_data(&self) -> u8 {
+        self.saved_data
+    }
+
+    /// Used when memory under an enabled site is overwritten, the `int3` stays and the new byte
+    /// is what comes back once the site is disabled.
+    pub(crate) fn set_saved_data(&mut self, data: u8) {
+        self.saved_data = data;
+    }
+
+    /// Moves the site over to a forked child, whose memory is a copy of ours.
+    pub(crate) fn set_pid(&mut self, pid: Pid) {
+        self.pid = pid;
+    }
+
+    /// The `int3` is gone without us putting the byte back, e.g. after an exec replaced the
+    /// memory. The site counts as disabled again and `enable` patches it in anew.
+    pub(crate) fn mark_removed(&mut self) {
+        self.is_enabled = false;
+        self.hardware_slot = None;
+    }
+
+    /// Patches in the `int3`, only valid for software sites.
+    pub(crate) fn enable(&mut self) -> Result<()> {
+        debug_assert!(!self.is_hardware);
+        if self.is_enabled {
+            return Ok(());
+        }
+        // PEEKDATA/POKEDATA work a word at a time, only the lowest byte belongs to the breakpoint
+        let word = ptrace::read(self.pid, self.address.addr() as ptrace::AddressType)
+            .map_err(Error::memory(self.address, self.pid))? as u64;
+        self.saved_data = (word & 0xff) as u8;
+        let patched = (word & !0xff) | INT3 as u64;
+        ptrace::write(self.pid, self.address.addr() as ptrace::AddressType, patched as i64)
+            .map_err(Error::memory(self.address, self.pid))?;
+        self.is_enabled = true;
+        Ok(())
+    }
+
+    pub(crate) fn disable(&mut self) -> Result<()> {
+        debug_assert!(!self.is_hardware);
+        if !self.is_enabled {
+            return Ok(());
+        }
+        let word = ptrace::read(self.pid, self.address.addr() as ptrace::AddressType)
+            .map_err(Error::memory(self.address, self.pid))? as u64;
+        let restored = (word & !0xff) | self.saved_data as u64;
+        ptrace::write(self.pid, self.address.addr() as ptrace::AddressType, restored as i64)
+            .map_err(Error::memory(self.address, self.pid))?;
+        self.is_enabled = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use gimli::{BaseAddresses, CfaRule, DebugFrame, EhFrame, LittleEndian, RegisterRule, UnwindContext, UnwindSection, X86_64};
+use crate::rdb::elf::Elf;
+use crate::rdb::line_table::DwarfSlice;
+use crate::rdb::register_info::{Register, RegisterId};
+
+/// Where a function keeps the address it returns to while running some instruction, from the
+/// call frame information the compiler emits for every function, frame pointer or not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReturnAddressRule {
+    /// The canonical frame address, the stack pointer right before the call, is the value of
+    /// this register plus `cfa_offset`.
+    pub cfa_register: RegisterId,
+    pub cfa_offset: i64,
+    /// The return address is saved at the canonical frame address plus this.
+    pub offset: i64,
+}
+
+impl ReturnAddressRule {
+    /// The canonical frame address for the value `register` has, see [`ReturnAddressRule::cfa_register`].
+    pub fn cfa(&self, register: u64) -> u64 {
+        register.wrapping_add_signed(self.cfa_offset)
+    }
+
+    /// Where the return address is saved, given the canonical frame address.
+    pub fn slot(&self, cfa: u64) -> u64 {
+        cfa.wrapping_add_signed(self.offset)
+    }
+}
+
+/// The rule at the link time address `address` of `elf`, from `.eh_frame` or else the
+/// `.debug_frame` of its debug information. `None` when neither covers the address or the
+/// rule takes a DWARF expression to work out.
+pub fn return_address_rule(elf: &Elf, address: u64) -> Option<ReturnAddressRule> {
+    if let (Some(header), Some(data)) = (elf.section(".eh_frame"), elf.section_contents(".eh_frame")) {
+        // pointers in .eh_frame are mostly relative to the section itself
+        let mut bases = BaseAddresses::default().set_eh_frame(header.sh_addr);
+        if let Some(text) = elf.section(".text") {
+            bases = bases.set_text(text.sh_addr);
+        }
+        let rule = rule_at(&EhFrame::new(data, LittleEndian), &bases, address);
+        if rule.is_some() {
+            return rule;
+        }
+    }
+    let debug = elf.debug_object()?;
+    let data = debug.section_data(".debug_frame").ok()??;
+    let mut section = DebugFrame::new(&data, LittleEndian);
+    section.set_address_size(8);
+    rule_at(&section, &BaseAddresses::default(), address)
+}
+
+fn rule_at<'a, S: UnwindSection<DwarfSlice<'a>>>(section: &S, bases: &BaseAddresses, address: u64) -> Option<ReturnAddressRule> {
+    let mut context = UnwindContext::new();
+    let row = section.unwind_info_for_address(bases, &mut context, address, S::cie_from_offset).ok()?;
+    let CfaRule::RegisterAndOffset { register, offset: cfa_offset } = *row.cfa() else {
+        return None;
+    };
+    let RegisterRule::Offset(offset) = row.register(X86_64::RA)? else {
+        return None;
+    };
+    Some(ReturnAddressRule {
+        cfa_register: Register::by_dwarf_id(register.0 as i32)?.id,
+        cfa_offset,
+        offset,
+    })
+}
@@ -0,0 +1,54 @@
+use std::path::Path;
+use crate::rdb::elf::Elf;
+
+/// Where distributions install separate debug files, e.g. from `-dbgsym` packages.
+pub const DEBUG_ROOT: &str = "/usr/lib/debug";
+
+/// Finds the separate debug file of a stripped `elf` the way GDB does: by build id as
+/// `<debug_root>/.build-id/ab/cdef....debug`, then by the `.gnu_debuglink` name next to the
+/// object, in a `.debug` directory beside it and under `debug_root`. A build id file has to
+/// carry the same build id, a debuglink file has to match the CRC.
+pub fn find_debug_file(elf: &Elf, debug_root: &Path) -> Option<Elf> {
+    if let Some(id) = elf.build_id().filter(|id| id.len() >= 2) {
+        let hex: String = id.iter().map(|b| format!("{:02x}", b)).collect();
+        let path = debug_root.join(".build-id").join(&hex[..2]).join(format!("{}.debug", &hex[2..]));
+        if let Ok(debug_file) = Elf::open(path)
+            && debug_file.build_id() == Some(id) {
+            return Some(debug_file);
+        }
+    }
+    let (name, crc) = elf.debuglink()?;
+    let dir = elf.path().parent()?;
+    let candidates = [
+        dir.join(name),
+        dir.join(".debug").join(name),
+        debug_root.join(dir.strip_prefix("/").unwrap_or(dir)).join(name),
+    ];
+    candidates
+        .iter()
+        .filter_map(|path| Elf::open(path).ok())
+        // the link may name the object itself
+        .filter(|debug_file| debug_file.path() != elf.path())
+        .find(|debug_file| crc32(debug_file.data()) == crc)
+}
+
+/// The CRC-32 (IEEE 802.3, as zlib computes it) `.gnu_debuglink` stores.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
@@ -0,0 +1,118 @@
+use std::rc::Rc;
+use iced_x86::{Decoder, DecoderOptions, Formatter, GasFormatter, IntelFormatter, Mnemonic, OpKind, SymbolResolver, SymbolResult};
+use crate::error::{Error, Result};
+use crate::rdb::address::VirtAddr;
+use crate::rdb::elf::Elf;
+use crate::rdb::process::Process;
+
+/// Longest possible x86-64 instruction.
+const MAX_INSTRUCTION_LENGTH: usize = 15;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Syntax {
+    Att,
+    Intel,
+}
+
+impl Syntax {
+    pub fn parse(text: &str) -> Result<Self> {
+        match text {
+            "att" => Ok(Syntax::Att),
+            "intel" => Ok(Syntax::Intel),
+            _ => Err(Error::Parse(format!("Invalid syntax, expected att or intel: {}", text))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub address: VirtAddr,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+/// Disassembles up to `count` instructions starting at `address`. Memory is read without our
+/// `int3`s so breakpoint sites show the instruction they replaced. Fewer instructions come back
+/// when the code runs into unmapped memory.
+pub fn disassemble(process: &Process, address: VirtAddr, count: usize, syntax: Syntax) -> Result<Vec<Instruction>> {
+    let code = read_code(process, address, count * MAX_INSTRUCTION_LENGTH)?;
+    Ok(decode_with_symbols(&code, address, count, syntax, process.shared_elf()))
+}
+
+/// Up to `amount` bytes of code without our `int3`s, less when they run into unmapped memory.
+fn read_code(process: &Process, address: VirtAddr, amount: usize) -> Result<Vec<u8>> {
+    match process.read_memory_without_traps(address, amount) {
+        // the last instructions can sit right before an unmapped page, decode what is there
+        Err(Error::MemoryAccess { addr }) if addr > address => {
+            process.read_memory_without_traps(address, (addr - address) as usize)
+        }
+        result => result,
+    }
+}
+
+/// Where a call at `address` returns to, `None` when the instruction there is no call.
+pub(crate) fn call_return_address(process: &Process, address: VirtAddr) -> Result<Option<VirtAddr>> {
+    let code = read_code(process, address, MAX_INSTRUCTION_LENGTH)?;
+    let instruction = Decoder::with_ip(64, &code, address.addr(), DecoderOptions::NONE).decode();
+    let is_call = instruction.mnemonic() == Mnemonic::Call;
+    Ok(is_call.then(|| VirtAddr::new(instruction.next_ip())))
+}
+
+/// Decodes up to `count` instructions out of `code`, which is mapped at `address`. Stops early
+/// rather than returning an instruction cut off by the end of `code`.
+pub fn decode(code: &[u8], address: VirtAddr, count: usize, syntax: Syntax) -> Vec<Instruction> {
+    decode_with_symbols(code, address, count, syntax, None)
+}
+
+/// Like [`decode`], with branch targets and rip relative operands that land in `elf` shown as
+/// `0x401136 <main+0x10>`. `elf` has to have its load bias set for `address` to match.
+pub fn decode_with_symbols(code: &[u8], address: VirtAddr, count: usize, syntax: Syntax, elf: Option<Rc<Elf>>) -> Vec<Instruction> {
+    let mut decoder = Decoder::with_ip(64, code, address.addr(), DecoderOptions::NONE);
+    let resolver = elf.map(|elf| Box::new(ElfSymbolResolver { elf }) as Box<dyn SymbolResolver>);
+    let mut formatter: Box<dyn Formatter> = match syntax {
+        Syntax::Att => Box::new(GasFormatter::with_options(resolver, None)),
+        Syntax::Intel => Box::new(IntelFormatter::with_options(resolver, None)),
+    };
+    // same hex style as everything else we print, whatever the syntax
+    formatter.options_mut().set_uppercase_hex(false);
+    formatter.options_mut().set_hex_prefix("0x");
+    formatter.options_mut().set_hex_suffix("");
+    let mut instructions = Vec::new();
+    while instructions.len() < count && decoder.can_decode() {
+        let offset = decoder.position();
+        let instruction = decoder.decode();
+        if instruction.is_invalid() && decoder.last_error() == iced_x86::DecoderError::NoMoreBytes {
+            break;
+        }
+        let mut text = String::new();
+        formatter.format(&instruction, &mut text);
+        instructions.push(Instruction {
+            address: VirtAddr::new(instruction.ip()),
+            bytes: code[offset..offset + instruction.len()].to_vec(),
+            text,
+        });
+    }
+    instructions
+}
+
+/// Names the addresses jumps, calls and rip relative operands refer to. Immediates and other
+/// displacements are left alone, they are rarely addresses.
+struct ElfSymbolResolver {
+    elf: Rc<Elf>,
+}
+
+impl SymbolResolver for ElfSymbolResolver {
+    fn symbol(&mut self, instruction: &iced_x86::Instruction, _operand: u32, instruction_operand: Option<u32>, address: u64, _address_size: u32) -> Option<SymbolResult<'_>> {
+        let operand = instruction_operand?;
+        let refers_to_address = match instruction.op_kind(operand) {
+            OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => true,
+            OpKind::Memory => instruction.is_ip_rel_memory_operand(),
+            _ => false,
+        };
+        if !refers_to_address {
+            return None;
+        }
+        let location = self.elf.symbol_at_virt_addr(VirtAddr::new(address))?;
+        Some(SymbolResult::with_string(address, format!("{:#x} <{}>", address, location)))
+    }
+}
@@ -0,0 +1,497 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::c_void;
+use std::fmt;
+use std::fs::File;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use nix::libc;
+use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+use crate::error::{Error, Result};
+use crate::rdb::address::{FileAddr, VirtAddr};
+use crate::rdb::debug_file;
+use crate::rdb::function_table::{Function, FunctionTable};
+use crate::rdb::interval_index::IntervalIndex;
+use crate::rdb::line_table::{LineEntry, LineTable};
+
+// not all of these are in libc
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHT_DYNSYM: u32 = 11;
+pub const SHF_ALLOC: u64 = 0x2;
+pub const SHF_COMPRESSED: u64 = 0x800;
+pub const ELFCOMPRESS_ZLIB: u32 = 1;
+pub const NT_GNU_BUILD_ID: u32 = 3;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STB_GLOBAL: u8 = 1;
+const SHN_XINDEX: u16 = 0xffff;
+const PN_XNUM: u16 = 0xffff;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+
+/// A symbol from `.symtab` or `.dynsym`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The name as it is in the string table.
+    pub name: String,
+    /// `name` demangled if it is a Rust or C++ name, `name` again otherwise.
+    pub demangled_name: String,
+    /// The link time address, not where the symbol ends up in a running process.
+    pub value: u64,
+    pub size: u64,
+    /// `STT_*`, the low four bits of `st_info`.
+    pub kind: u8,
+    /// `STB_*`, the high four bits of `st_info`.
+    pub binding: u8,
+    /// The section the symbol is defined in, 0 for undefined symbols.
+    pub section_index: u16,
+}
+
+impl Symbol {
+    pub fn is_function(&self) -> bool {
+        self.kind == STT_FUNC
+    }
+
+    pub fn is_defined(&self) -> bool {
+        self.section_index != 0
+    }
+}
+
+/// An address as a symbol and how far into it the address is, shown as `main+0x20`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolLocation<'a> {
+    pub symbol: &'a Symbol,
+    pub offset: u64,
+}
+
+impl fmt::Display for SymbolLocation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            0 => write!(f, "{}", self.symbol.demangled_name),
+            offset => write!(f, "{}+{:#x}", self.symbol.demangled_name, offset),
+        }
+    }
+}
+
+/// Demangles Rust (legacy and v0) and Itanium C++ names, anything else comes back as it is.
+pub fn demangle(name: &str) -> String {
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        // the alternate form leaves out the hash of legacy names
+        return format!("{:#}", demangled);
+    }
+    if name.starts_with("_Z")
+        && let Ok(symbol) = cpp_demangle::Symbol::new(name)
+        && let Ok(demangled) = symbol.demangle(&cpp_demangle::DemangleOptions::default()) {
+        return demangled;
+    }
+    name.to_string()
+}
+
+/// A private read-only mapping of a whole file, unmapped on drop.
+struct Mapping {
+    address: NonNull<c_void>,
+    len: usize,
+}
+
+impl Mapping {
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.address.as_ptr() as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        let _ = unsafe { munmap(self.address, self.len) };
+    }
+}
+
+/// A 64-bit little endian ELF executable or shared object, mapped into our memory. The headers
+/// and the symbol table are parsed up front, everything else is read from the mapping when
+/// asked for.
+pub struct Elf {
+    path: PathBuf,
+    mapping: Mapping,
+    header: libc::Elf64_Ehdr,
+    section_headers: Vec<libc::Elf64_Shdr>,
+    program_headers: Vec<libc::Elf64_Phdr>,
+    section_indices: HashMap<String, usize>,
+    symbols: Vec<Symbol>,
+    /// Indices into `symbols` by mangled and demangled name, C++ names also without parameters.
+    symbols_by_name: HashMap<String, Vec<usize>>,
+    /// Function and object symbols by the link time addresses they cover.
+    symbols_by_address: IntervalIndex<usize>,
+    /// How far the object was moved from its link time addresses when it was loaded.
+    load_bias: u64,
+    /// The separate debug file of a stripped object, looked for on first use.
+    debug_file: OnceCell<Option<Box<Elf>>>,
+    /// Parsed on first use, from the object itself or its separate debug file.
+    line_table: OnceCell<Option<LineTable>>,
+    function_table: OnceCell<Option<FunctionTable>>,
+}
+
+impl Elf {
+    /// Maps and parses the file at `path`. The path is resolved, so opening `/proc/pid/exe`
+    /// reports the executable it links to.
+    pub fn open(path: impl AsRef<Path>) -> Result<Elf> {
+        let path = path.as_ref();
+        let error = |message: String| Error::Elf { path: path.to_path_buf(), message };
+        let file = File::open(path).map_err(|e| error(e.to_string()))?;
+        let len = file.metadata().map_err(|e| error(e.to_string()))?.len() as usize;
+        let Some(non_zero_len) = NonZeroUsize::new(len) else {
+            return Err(error("Empty file".to_string()));
+        };
+        let address = unsafe { mmap(None, non_zero_len, ProtFlags::PROT_READ, MapFlags::MAP_PRIVATE, &file, 0) }
+            .map_err(|errno| error(format!("mmap failed: {}", errno)))?;
+        let mapping = Mapping { address, len };
+
+        let header: libc::Elf64_Ehdr = read_struct(mapping.bytes(), 0)
+            .filter(|h: &libc::Elf64_Ehdr| h.e_ident[..4] == *b"\x7fELF")
+            .ok_or_else(|| error("Not an ELF file".to_string()))?;
+        if header.e_ident[libc::EI_CLASS] != ELFCLASS64 || header.e_ident[libc::EI_DATA] != ELFDATA2LSB {
+            return Err(error("Only 64-bit little endian ELF files are supported".to_string()));
+        }
+
+        let section_headers = parse_section_headers(mapping.bytes(), &header)
+            .ok_or_else(|| error("Section headers out of bounds".to_string()))?;
+        let program_headers = parse_program_headers(mapping.bytes(), &header, &section_headers)
+            .ok_or_else(|| error("Program headers out of bounds".to_string()))?;
+
+        let mut elf = Elf {
+            path: std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+            mapping,
+            header,
+            section_headers,
+            program_headers,
+            section_indices: HashMap::new(),
+            symbols: Vec::new(),
+            symbols_by_name: HashMap::new(),
+            symbols_by_address: IntervalIndex::new([]),
+            load_bias: 0,
+            debug_file: OnceCell::new(),
+            line_table: OnceCell::new(),
+            function_table: OnceCell::new(),
+        };
+        elf.section_indices = (0..elf.section_headers.len())
+            .filter_map(|index| Some((elf.section_name(index)?.to_string(), index)))
+            .collect();
+        elf.symbols = elf.parse_symbols();
+        for (index, symbol) in elf.symbols.iter().enumerate() {
+            let mut names = vec![symbol.name.as_str(), symbol.demangled_name.as_str()];
+            // `break ns::function` without spelling out the parameters
+            names.extend(symbol.demangled_name.split_once('(').map(|(name, _)| name));
+            names.dedup();
+            for name in names {
+                elf.symbols_by_name.entry(name.to_string()).or_default().push(index);
+            }
+        }
+        elf.symbols_by_address = elf.index_symbols_by_address();
+        Ok(elf)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The whole file.
+    pub fn data(&self) -> &[u8] {
+        self.mapping.bytes()
+    }
+
+    pub fn header(&self) -> &libc::Elf64_Ehdr {
+        &self.header
+    }
+
+    /// Zero until the object is known to be loaded somewhere else than it was linked for, as a
+    /// position independent executable always is.
+    pub fn load_bias(&self) -> u64 {
+        self.load_bias
+    }
+
+    pub(crate) fn set_load_bias(&mut self, load_bias: u64) {
+        self.load_bias = load_bias;
+    }
+
+    /// `e_entry`, the link time address of the entry point.
+    pub fn entry(&self) -> FileAddr<'_> {
+        FileAddr::new(self, self.header.e_entry)
+    }
+
+    pub fn section_headers(&self) -> &[libc::Elf64_Shdr] {
+        &self.section_headers
+    }
+
+    pub fn program_headers(&self) -> &[libc::Elf64_Phdr] {
+        &self.program_headers
+    }
+
+    /// The name of the section at `index`, from the section header string table.
+    pub fn section_name(&self, index: usize) -> Option<&str> {
+        let names = self.section_headers.get(self.section_name_table_index())?;
+        self.string_at(names, self.section_headers.get(index)?.sh_name as usize)
+    }
+
+    pub fn section(&self, name: &str) -> Option<&libc::Elf64_Shdr> {
+        self.section_indices.get(name).map(|index| &self.section_headers[*index])
+    }
+
+    /// The bytes of the section in the file, empty for sections like `.bss` that take no space.
+    pub fn section_contents(&self, name: &str) -> Option<&[u8]> {
+        self.contents_of(self.section(name)?)
+    }
+
+    /// Like [`Elf::section_contents`] but inflates `SHF_COMPRESSED` sections, as debug sections
+    /// often are.
+    pub fn section_data(&self, name: &str) -> Result<Option<Cow<'_, [u8]>>> {
+        let Some(section) = self.section(name) else {
+            return Ok(None);
+        };
+        let error = |message: &str| Error::Elf { path: self.path.clone(), message: format!("{}: {}", name, message) };
+        let contents = self.contents_of(section).ok_or_else(|| error("Section out of bounds"))?;
+        if section.sh_flags & SHF_COMPRESSED == 0 {
+            return Ok(Some(Cow::Borrowed(contents)));
+        }
+        let header: libc::Elf64_Chdr = read_struct(contents, 0).ok_or_else(|| error("Truncated compression header"))?;
+        if header.ch_type != ELFCOMPRESS_ZLIB {
+            return Err(error("Only zlib compressed sections are supported"));
+        }
+        let compressed = &contents[size_of::<libc::Elf64_Chdr>()..];
+        let inflated = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(compressed, header.ch_size as usize)
+            .map_err(|_| error("Corrupt compressed section"))?;
+        Ok(Some(Cow::Owned(inflated)))
+    }
+
+    /// The id the linker put in `.note.gnu.build-id`, the same in the executable and its
+    /// separate debug file.
+    pub fn build_id(&self) -> Option<&[u8]> {
+        let note = self.section_contents(".note.gnu.build-id")?;
+        let word = |offset: usize| Some(u32::from_le_bytes(note.get(offset..offset + 4)?.try_into().ok()?));
+        let (name_size, desc_size, kind) = (word(0)? as usize, word(4)? as usize, word(8)?);
+        let desc_start = 12 + name_size.next_multiple_of(4);
+        if kind != NT_GNU_BUILD_ID || note.get(12..12 + name_size)? != b"GNU\0" {
+            return None;
+        }
+        note.get(desc_start..desc_start.checked_add(desc_size)?)
+    }
+
+    /// The file name and CRC32 of the separate debug file from `.gnu_debuglink`.
+    pub fn debuglink(&self) -> Option<(&str, u32)> {
+        let link = self.section_contents(".gnu_debuglink")?;
+        let name_len = link.iter().position(|&b| b == 0)?;
+        let crc_start = (name_len + 1).next_multiple_of(4);
+        let crc = u32::from_le_bytes(link.get(crc_start..crc_start + 4)?.try_into().ok()?);
+        Some((std::str::from_utf8(&link[..name_len]).ok()?, crc))
+    }
+
+    /// The object the debug information is in, this one unless it has been stripped and its
+    /// separate debug file was found.
+    pub fn debug_object(&self) -> Option<&Elf> {
+        if self.section(".debug_line").is_some() || self.section(".debug_info").is_some() {
+            return Some(self);
+        }
+        self.debug_file
+            .get_or_init(|| debug_file::find_debug_file(self, Path::new(debug_file::DEBUG_ROOT)).map(Box::new))
+            .as_deref()
+    }
+
+    /// The line table from `.debug_line`, out of the separate debug file when the object has
+    /// been stripped. `None` when there is no usable debug information.
+    pub fn line_table(&self) -> Option<&LineTable> {
+        self.line_table.get_or_init(|| LineTable::parse(self.debug_object()?).ok()).as_ref()
+    }
+
+    /// The functions from `.debug_info`, like [`Elf::line_table`].
+    pub fn function_table(&self) -> Option<&FunctionTable> {
+        self.function_table.get_or_init(|| FunctionTable::parse(self.debug_object()?).ok()).as_ref()
+    }
+
+    /// The line table row covering `address`.
+    pub fn line_entry_at(&self, address: FileAddr<'_>) -> Option<&LineEntry> {
+        if !self.owns(address) {
+            return None;
+        }
+        self.line_table()?.entry_at(address.addr())
+    }
+
+    /// The functions covering `address`, see [`FunctionTable::frames_at`].
+    pub fn function_frames_at(&self, address: FileAddr<'_>) -> Vec<&Function> {
+        if !self.owns(address) {
+            return Vec::new();
+        }
+        self.function_table().map(|table| table.frames_at(address.addr())).unwrap_or_default()
+    }
+
+    /// The allocated section whose link time address range holds `address`.
+    pub fn section_containing(&self, address: FileAddr<'_>) -> Option<&libc::Elf64_Shdr> {
+        if !self.owns(address) {
+            return None;
+        }
+        let address = address.addr();
+        self.section_headers.iter().find(|s| {
+            s.sh_flags & SHF_ALLOC != 0 && s.sh_addr <= address && address < s.sh_addr.saturating_add(s.sh_size)
+        })
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Every symbol called `name`, there can be several, e.g. static functions in different files.
+    pub fn symbols_by_name<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Symbol> + 'a {
+        self.symbols_by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|index| &self.symbols[*index])
+    }
+
+    /// The defined function called `name`, e.g. `main`.
+    pub fn function(&self, name: &str) -> Option<&Symbol> {
+        self.symbols_by_name(name).find(|s| s.is_function() && s.is_defined() && s.value != 0)
+    }
+
+    /// The function or object covering `address`.
+    pub fn symbol_containing(&self, address: FileAddr<'_>) -> Option<SymbolLocation<'_>> {
+        if !self.owns(address) {
+            return None;
+        }
+        let symbol = &self.symbols[*self.symbols_by_address.find(address.addr())?];
+        Some(SymbolLocation { symbol, offset: address.addr() - symbol.value })
+    }
+
+    /// The function or object covering `address` in the process the object is loaded in.
+    pub fn symbol_at_virt_addr(&self, address: VirtAddr) -> Option<SymbolLocation<'_>> {
+        self.symbol_containing(address.to_file_addr(self)?)
+    }
+
+    /// The `PT_LOAD` segment whose link time address range holds `address`.
+    pub(crate) fn load_segment_containing(&self, address: u64) -> Option<&libc::Elf64_Phdr> {
+        self.program_headers.iter().find(|p| {
+            p.p_type == libc::PT_LOAD && p.p_vaddr <= address && address < p.p_vaddr.saturating_add(p.p_memsz)
+        })
+    }
+
+    /// Link time addresses only mean something for the object they came from, lookups with an
+    /// address of another object find nothing.
+    fn owns(&self, address: FileAddr<'_>) -> bool {
+        std::ptr::eq(address.elf(), self)
+    }
+
+    /// Zero sized symbols cover just their address. Where symbols share an address, global
+    /// functions are preferred.
+    fn index_symbols_by_address(&self) -> IntervalIndex<usize> {
+        let mut indexed: Vec<usize> = (0..self.symbols.len())
+            .filter(|i| {
+                let symbol = &self.symbols[*i];
+                symbol.is_defined() && symbol.value != 0 && (symbol.is_function() || symbol.kind == STT_OBJECT)
+            })
+            .collect();
+        // the index picks the last added of equal ranges
+        indexed.sort_by_key(|i| (self.symbols[*i].binding == STB_GLOBAL, self.symbols[*i].is_function()));
+        IntervalIndex::new(indexed.into_iter().map(|i| {
+            let symbol = &self.symbols[i];
+            (symbol.value..symbol.value.saturating_add(symbol.size.max(1)), i)
+        }))
+    }
+
+    fn contents_of(&self, section: &libc::Elf64_Shdr) -> Option<&[u8]> {
+        if section.sh_type == SHT_NOBITS {
+            return Some(&[]);
+        }
+        let start = section.sh_offset as usize;
+        self.data().get(start..start.checked_add(section.sh_size as usize)?)
+    }
+
+    fn section_name_table_index(&self) -> usize {
+        match self.header.e_shstrndx {
+            // too large for the header, the real index is in the first section header
+            SHN_XINDEX => self.section_headers.first().map_or(0, |s| s.sh_link as usize),
+            index => index as usize,
+        }
+    }
+
+    /// The NUL terminated string at `offset` in the string table `table`.
+    fn string_at(&self, table: &libc::Elf64_Shdr, offset: usize) -> Option<&str> {
+        let strings = self.contents_of(table)?.get(offset..)?;
+        let end = strings.iter().position(|b| *b == 0)?;
+        std::str::from_utf8(&strings[..end]).ok()
+    }
+
+    /// `.symtab` and `.dynsym` together, a symbol in both is kept once. Nameless symbols are left
+    /// out.
+    fn parse_symbols(&self) -> Vec<Symbol> {
+        let mut seen = HashSet::new();
+        let mut symbols = Vec::new();
+        for kind in [SHT_SYMTAB, SHT_DYNSYM] {
+            let Some(table) = self.section_headers.iter().find(|s| s.sh_type == kind) else {
+                continue;
+            };
+            let (Some(strings), Some(entries)) = (self.section_headers.get(table.sh_link as usize), self.contents_of(table)) else {
+                continue;
+            };
+            for bytes in entries.chunks_exact(size_of::<libc::Elf64_Sym>()) {
+                let Some(symbol) = read_struct::<libc::Elf64_Sym>(bytes, 0) else {
+                    continue;
+                };
+                let Some(name) = self.string_at(strings, symbol.st_name as usize).filter(|n| !n.is_empty()) else {
+                    continue;
+                };
+                // .dynsym mostly repeats what .symtab has
+                if !seen.insert((name, symbol.st_value)) {
+                    continue;
+                }
+                symbols.push(Symbol {
+                    name: name.to_string(),
+                    demangled_name: demangle(name),
+                    value: symbol.st_value,
+                    size: symbol.st_size,
+                    kind: symbol.st_info & 0xf,
+                    binding: symbol.st_info >> 4,
+                    section_index: symbol.st_shndx,
+                });
+            }
+        }
+        symbols
+    }
+}
+
+/// Copies a `T` out of `data` at `offset`, `None` if it does not fit. Only used with the libc
+/// ELF structs, which are plain integers for which any bytes are valid.
+fn read_struct<T: Copy>(data: &[u8], offset: usize) -> Option<T> {
+    data.get(offset..offset.checked_add(size_of::<T>())?)?;
+    Some(unsafe { std::ptr::read_unaligned(data[offset..].as_ptr() as *const T) })
+}
+
+fn parse_section_headers(data: &[u8], header: &libc::Elf64_Ehdr) -> Option<Vec<libc::Elf64_Shdr>> {
+    if header.e_shoff == 0 {
+        return Some(Vec::new());
+    }
+    let offset = header.e_shoff as usize;
+    let first: libc::Elf64_Shdr = read_struct(data, offset)?;
+    // with too many sections for e_shnum the count is in the first section header
+    let count = match header.e_shnum {
+        0 => first.sh_size as usize,
+        count => count as usize,
+    };
+    (0..count)
+        .map(|index| read_struct(data, offset.checked_add(index.checked_mul(size_of::<libc::Elf64_Shdr>())?)?))
+        .collect()
+}
+
+fn parse_program_headers(
+    data: &[u8], header: &libc::Elf64_Ehdr, sections: &[libc::Elf64_Shdr]
+) -> Option<Vec<libc::Elf64_Phdr>> {
+    if header.e_phoff == 0 {
+        return Some(Vec::new());
+    }
+    let count = match header.e_phnum {
+        PN_XNUM => sections.first()?.sh_info as usize,
+        count => count as usize,
+    };
+    let offset = header.e_phoff as usize;
+    (0..count)
+        .map(|index| read_struct(data, offset.checked_add(index.checked_mul(size_of::<libc::Elf64_Phdr>())?)?))
+        .collect()
+}
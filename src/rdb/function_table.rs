@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+use gimli::{AttributeValue, DebuggingInformationEntry, DwAt, Dwarf, EndianSlice, LittleEndian, Unit};
+use crate::error::Result;
+use crate::rdb::elf::Elf;
+use crate::rdb::interval_index::IntervalIndex;
+use crate::rdb::line_table::{dwarf_error, file_path, load_dwarf_sections, DwarfSlice};
+
+/// How many `DW_AT_abstract_origin` and `DW_AT_specification` links are followed looking for an
+/// attribute, they do not nest deeper than this in practice.
+const MAX_ORIGIN_DEPTH: usize = 8;
+
+/// A function from `.debug_info`, either one with its own code or a copy of one inlined into
+/// another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    /// The link time address ranges the code covers, more than one when the compiler split it.
+    pub ranges: Vec<Range<u64>>,
+    /// Where the code starts, the first instruction of an inlined copy need not be the lowest.
+    pub entry: u64,
+    pub return_type: ReturnType,
+    /// Where an inlined copy was called from, `None` for functions with their own code.
+    pub call_site: Option<(Rc<Path>, u64)>,
+    /// The function an inlined copy was inlined into, an index into [`FunctionTable::functions`].
+    pub parent: Option<usize>,
+}
+
+impl Function {
+    pub fn is_inlined(&self) -> bool {
+        self.call_site.is_some()
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        self.ranges.iter().any(|range| range.contains(&address))
+    }
+}
+
+/// What a function returns, enough to tell which register the value comes back in and how to
+/// show it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReturnType {
+    Void,
+    Bool,
+    Integer { size: u64, signed: bool },
+    Pointer,
+    /// Comes back in xmm0.
+    Float { size: u64 },
+    /// Structures and whatever else we do not interpret.
+    Other,
+}
+
+/// The `DW_TAG_subprogram` and `DW_TAG_inlined_subroutine` entries of every compile unit that
+/// have code.
+pub struct FunctionTable {
+    functions: Vec<Function>,
+    /// Indices into `functions` by the address ranges they cover, inlined copies lie within the
+    /// function they were inlined into so the innermost one is found.
+    functions_by_address: IntervalIndex<usize>,
+}
+
+impl FunctionTable {
+    pub fn parse(elf: &Elf) -> Result<FunctionTable> {
+        let error = dwarf_error(elf);
+        let sections = load_dwarf_sections(elf)?;
+        let dwarf = sections.borrow(|section| EndianSlice::new(section, LittleEndian));
+
+        let mut functions = Vec::new();
+        let mut ranges = Vec::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next().map_err(&error)? {
+            let unit = dwarf.unit(header).map_err(&error)?;
+            let mut files: HashMap<u64, Rc<Path>> = HashMap::new();
+            // the functions enclosing the current entry with their depth
+            let mut enclosing: Vec<(isize, usize)> = Vec::new();
+            let mut entries = unit.entries();
+            while let Some(entry) = entries.next_dfs().map_err(&error)? {
+                while enclosing.last().is_some_and(|(depth, _)| *depth >= entry.depth()) {
+                    enclosing.pop();
+                }
+                let inlined = match entry.tag() {
+                    gimli::DW_TAG_subprogram => false,
+                    gimli::DW_TAG_inlined_subroutine => true,
+                    _ => continue,
+                };
+                let mut function_ranges = Vec::new();
+                let mut die_ranges = dwarf.die_ranges(&unit, entry).map_err(&error)?;
+                while let Some(range) = die_ranges.next().map_err(&error)? {
+                    function_ranges.push(range.begin..range.end);
+                }
+                // declarations and functions the linker threw away, which are moved to address 0
+                if function_ranges.is_empty() || function_ranges.iter().any(|range| range.start == 0) {
+                    continue;
+                }
+                let entry_pc = match entry.attr_value(gimli::DW_AT_entry_pc) {
+                    Some(value) => dwarf.attr_address(&unit, value).map_err(&error)?,
+                    None => None,
+                };
+                let call_site = match (inlined, entry.attr_value(gimli::DW_AT_call_file)) {
+                    (true, Some(AttributeValue::FileIndex(index))) => {
+                        let file = match files.get(&index) {
+                            Some(file) => file.clone(),
+                            None => {
+                                let file: Rc<Path> = match &unit.line_program {
+                                    Some(program) => {
+                                        let header = program.header();
+                                        file_path(&dwarf, &unit, header, header.file(index)).map_err(&error)?.into()
+                                    }
+                                    None => Path::new("").into(),
+                                };
+                                files.insert(index, file.clone());
+                                file
+                            }
+                        };
+                        let line = entry.attr_value(gimli::DW_AT_call_line).and_then(|line| line.udata_value()).unwrap_or(0);
+                        Some((file, line))
+                    }
+                    _ => None,
+                };
+                let index = functions.len();
+                ranges.extend(function_ranges.iter().map(|range| (range.clone(), index)));
+                functions.push(Function {
+                    name: function_name(&dwarf, &unit, entry).unwrap_or_default(),
+                    entry: entry_pc.unwrap_or_else(|| function_ranges.iter().map(|range| range.start).min().unwrap()),
+                    ranges: function_ranges,
+                    return_type: return_type(&unit, entry),
+                    call_site,
+                    parent: enclosing.last().map(|(_, parent)| *parent),
+                });
+                enclosing.push((entry.depth(), index));
+            }
+        }
+        Ok(FunctionTable { functions, functions_by_address: IntervalIndex::new(ranges) })
+    }
+
+    pub fn functions(&self) -> &[Function] {
+        &self.functions
+    }
+
+    /// The innermost function covering the link time address `address`, an inlined copy if the
+    /// code there was inlined.
+    pub fn function_at(&self, address: u64) -> Option<&Function> {
+        self.functions_by_address.find(address).map(|index| &self.functions[*index])
+    }
+
+    /// The functions covering `address` from the innermost inlined copy out to the function
+    /// with its own code.
+    pub fn frames_at(&self, address: u64) -> Vec<&Function> {
+        let mut frames = Vec::new();
+        let mut next = self.functions_by_address.find(address).copied();
+        while let Some(index) = next {
+            let function = &self.functions[index];
+            frames.push(function);
+            next = function.parent.filter(|_| function.is_inlined());
+        }
+        frames
+    }
+}
+
+/// An attribute of `entry`, or of the entries its abstract origin or specification refer to,
+/// which is where inlined copies and out of line definitions keep their name and type.
+fn origin_attr<'a>(
+    unit: &Unit<DwarfSlice<'a>>,
+    entry: &DebuggingInformationEntry<DwarfSlice<'a>>,
+    name: DwAt,
+) -> Option<AttributeValue<DwarfSlice<'a>>> {
+    let mut entry = entry.clone();
+    for _ in 0..MAX_ORIGIN_DEPTH {
+        if let Some(value) = entry.attr_value(name) {
+            return Some(value);
+        }
+        let origin = entry.attr_value(gimli::DW_AT_abstract_origin)
+            .or_else(|| entry.attr_value(gimli::DW_AT_specification));
+        // references into other units only come with LTO, those stay unnamed
+        let Some(AttributeValue::UnitRef(offset)) = origin else {
+            return None;
+        };
+        entry = unit.entry(offset).ok()?;
+    }
+    None
+}
+
+fn function_name<'a>(dwarf: &Dwarf<DwarfSlice<'a>>, unit: &Unit<DwarfSlice<'a>>, entry: &DebuggingInformationEntry<DwarfSlice<'a>>) -> Option<String> {
+    let name = origin_attr(unit, entry, gimli::DW_AT_name)?;
+    Some(dwarf.attr_string(unit, name).ok()?.to_string_lossy().into_owned())
+}
+
+fn return_type<'a>(unit: &Unit<DwarfSlice<'a>>, entry: &DebuggingInformationEntry<DwarfSlice<'a>>) -> ReturnType {
+    let Some(mut value) = origin_attr(unit, entry, gimli::DW_AT_type) else {
+        return ReturnType::Void;
+    };
+    for _ in 0..MAX_ORIGIN_DEPTH {
+        let AttributeValue::UnitRef(offset) = value else {
+            return ReturnType::Other;
+        };
+        let Ok(ty) = unit.entry(offset) else {
+            return ReturnType::Other;
+        };
+        let size = ty.attr_value(gimli::DW_AT_byte_size).and_then(|size| size.udata_value()).unwrap_or(0);
+        match ty.tag() {
+            gimli::DW_TAG_typedef | gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type | gimli::DW_TAG_restrict_type | gimli::DW_TAG_atomic_type => {
+                match ty.attr_value(gimli::DW_AT_type) {
+                    Some(next) => value = next,
+                    // a qualified void
+                    None => return ReturnType::Void,
+                }
+            }
+            gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type | gimli::DW_TAG_rvalue_reference_type => return ReturnType::Pointer,
+            gimli::DW_TAG_enumeration_type => return ReturnType::Integer { size, signed: true },
+            gimli::DW_TAG_base_type => {
+                return match ty.attr_value(gimli::DW_AT_encoding) {
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_float)) => ReturnType::Float { size },
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_boolean)) => ReturnType::Bool,
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_signed | gimli::DW_ATE_signed_char)) => {
+                        ReturnType::Integer { size, signed: true }
+                    }
+                    Some(AttributeValue::Encoding(_)) => ReturnType::Integer { size, signed: false },
+                    _ => ReturnType::Other,
+                };
+            }
+            _ => return ReturnType::Other,
+        }
+    }
+    ReturnType::Other
+}
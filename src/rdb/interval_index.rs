@@ -0,0 +1,45 @@
+use std::ops::Range;
+
+/// Half-open address ranges mapped to values, looked up by an address inside them. Ranges may
+/// overlap, the one that starts last wins, so nested ranges resolve to the innermost one. Among
+/// ranges with the same start the shortest wins, then the one added last.
+pub struct IntervalIndex<T> {
+    /// Sorted by start, longer ranges first.
+    entries: Vec<(Range<u64>, T)>,
+    /// The largest end of `entries[..=i]`, a backwards search stops once nothing before can
+    /// reach the address.
+    max_ends: Vec<u64>,
+}
+
+impl<T> IntervalIndex<T> {
+    pub fn new(entries: impl IntoIterator<Item = (Range<u64>, T)>) -> Self {
+        let mut entries: Vec<(Range<u64>, T)> = entries.into_iter().filter(|(range, _)| !range.is_empty()).collect();
+        // stable, ranges that compare equal keep the order they were added in
+        entries.sort_by(|(a, _), (b, _)| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+        let max_ends = entries
+            .iter()
+            .scan(0, |max_end, (range, _)| {
+                *max_end = range.end.max(*max_end);
+                Some(*max_end)
+            })
+            .collect();
+        Self { entries, max_ends }
+    }
+
+    pub fn find(&self, address: u64) -> Option<&T> {
+        let candidates = self.entries.partition_point(|(range, _)| range.start <= address);
+        (0..candidates)
+            .rev()
+            .take_while(|i| self.max_ends[*i] > address)
+            .find(|i| self.entries[*i].0.contains(&address))
+            .map(|i| &self.entries[i].1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
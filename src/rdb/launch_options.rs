@@ -0,0 +1,304 @@
+use std::collections::BTreeMap;
+use std::ffi::{CString, OsStr, OsString};
+use std::fs::{File, OpenOptions};
+use std::os::fd::OwnedFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+use nix::pty::openpty;
+use nix::sys::resource::Resource;
+use crate::error::{Error, Result};
+
+/// Where one of the inferior's standard streams goes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Stdio {
+    /// Shares ours.
+    #[default]
+    Inherit,
+    Null,
+    /// Read from for stdin, created or truncated for stdout and stderr.
+    File(PathBuf),
+    /// A pseudo-terminal allocated for the inferior, all streams set to `Pty` share it. What the
+    /// program writes is read through [`Process::terminal`](crate::rdb::process::Process::terminal).
+    Pty,
+}
+
+impl Stdio {
+    /// `inherit`, `null`, `pty`, anything else is a file path.
+    pub fn parse(text: &str) -> Self {
+        match text {
+            "inherit" => Stdio::Inherit,
+            "null" => Stdio::Null,
+            "pty" => Stdio::Pty,
+            path => Stdio::File(PathBuf::from(path)),
+        }
+    }
+}
+
+/// Where a freshly launched process first stops.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StopAt {
+    /// The SIGTRAP after exec, before the dynamic loader has run.
+    #[default]
+    Exec,
+    /// The ELF entry point, `_start`.
+    Entry,
+    /// `main`, or the entry point when the executable has no symbol for it.
+    Main,
+}
+
+impl StopAt {
+    pub fn parse(text: &str) -> Result<Self> {
+        match text {
+            "exec" => Ok(StopAt::Exec),
+            "entry" => Ok(StopAt::Entry),
+            "main" => Ok(StopAt::Main),
+            _ => Err(Error::Parse(format!("Invalid stop, expected exec, entry or main: {}", text))),
+        }
+    }
+}
+
+/// Everything needed to start, and later restart, an inferior. Built like
+/// `std::process::Command`:
+///
+/// ```no_run
+/// # use nix::sys::resource::Resource;
+/// # use rdb::rdb::launch_options::{LaunchOptions, Stdio};
+/// let options = LaunchOptions::new("./server")
+///     .args(["--port", "8080"])
+///     .env("RUST_LOG", "debug")
+///     .env_remove("http_proxy")
+///     .current_dir("/tmp")
+///     .stdout(Stdio::Pty)
+///     .rlimit(Resource::RLIMIT_CORE, 0, 0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchOptions {
+    program: String,
+    args: Vec<String>,
+    inherit_env: bool,
+    /// `None` unsets the variable.
+    env_changes: BTreeMap<String, Option<String>>,
+    current_dir: Option<PathBuf>,
+    stdin: Stdio,
+    stdout: Stdio,
+    stderr: Stdio,
+    stop_at: StopAt,
+    disable_randomization: bool,
+    /// Soft and hard limit.
+    rlimits: BTreeMap<Resource, (u64, u64)>,
+}
+
+impl LaunchOptions {
+    /// Runs `program` with no arguments in our environment and working directory, with address
+    /// space randomization disabled like GDB does. Programs without a `/` are looked up in `PATH`.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            inherit_env: true,
+            env_changes: BTreeMap::new(),
+            current_dir: None,
+            stdin: Stdio::Inherit,
+            stdout: Stdio::Inherit,
+            stderr: Stdio::Inherit,
+            stop_at: StopAt::Exec,
+            disable_randomization: true,
+            rlimits: BTreeMap::new(),
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I: IntoIterator<Item = S>, S: Into<String>>(mut self, args: I) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env_changes.insert(key.into(), Some(value.into()));
+        self
+    }
+
+    pub fn env_remove(mut self, key: impl Into<String>) -> Self {
+        self.env_changes.insert(key.into(), None);
+        self
+    }
+
+    /// Starts from an empty environment instead of ours, `env` calls still apply.
+    pub fn env_clear(mut self) -> Self {
+        self.inherit_env = false;
+        self.env_changes.retain(|_, value| value.is_some());
+        self
+    }
+
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.current_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn stdin(mut self, stdio: Stdio) -> Self {
+        self.stdin = stdio;
+        self
+    }
+
+    pub fn stdout(mut self, stdio: Stdio) -> Self {
+        self.stdout = stdio;
+        self
+    }
+
+    pub fn stderr(mut self, stdio: Stdio) -> Self {
+        self.stderr = stdio;
+        self
+    }
+
+    pub fn stop_at(mut self, stop_at: StopAt) -> Self {
+        self.stop_at = stop_at;
+        self
+    }
+
+    /// Sets `ADDR_NO_RANDOMIZE` in the inferior's personality so every run gets the same stack,
+    /// heap, mmap and PIE addresses. Best effort, some seccomp profiles (Docker's default among
+    /// them) refuse it and the program then starts randomized.
+    pub fn disable_randomization(mut self, disable: bool) -> Self {
+        self.disable_randomization = disable;
+        self
+    }
+
+    /// Sets a resource limit of the inferior, e.g. `RLIMIT_CORE`, `RLIMIT_STACK` or `RLIMIT_AS`.
+    /// Use `RLIM_INFINITY` for no limit. Raising a hard limit above ours fails the launch.
+    pub fn rlimit(mut self, resource: Resource, soft: u64, hard: u64) -> Self {
+        self.rlimits.insert(resource, (soft, hard));
+        self
+    }
+
+    pub fn get_disable_randomization(&self) -> bool {
+        self.disable_randomization
+    }
+
+    pub fn get_rlimits(&self) -> impl Iterator<Item = (Resource, u64, u64)> + '_ {
+        self.rlimits.iter().map(|(resource, (soft, hard))| (*resource, *soft, *hard))
+    }
+
+    pub fn get_stop_at(&self) -> StopAt {
+        self.stop_at
+    }
+
+    pub fn get_program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn get_args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn get_current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_deref()
+    }
+
+    /// The variables the inferior starts with, sorted by name.
+    pub fn environment(&self) -> Vec<(OsString, OsString)> {
+        let mut environment: BTreeMap<OsString, OsString> = if self.inherit_env {
+            std::env::vars_os().collect()
+        } else {
+            BTreeMap::new()
+        };
+        for (key, value) in &self.env_changes {
+            match value {
+                Some(value) => environment.insert(key.into(), value.into()),
+                None => environment.remove(OsStr::new(key)),
+            };
+        }
+        environment.into_iter().collect()
+    }
+
+    /// Everything exec needs as C strings, built before forking so the child does not allocate.
+    pub(crate) fn to_exec_args(&self) -> Result<ExecArgs> {
+        let invalid = |_| Error::Launch { errno: Errno::EINVAL };
+        let mut program = PathBuf::from(&self.program);
+        // a relative path means relative to us, not to the directory the inferior starts in
+        if self.current_dir.is_some() && self.program.contains('/') {
+            program = std::path::absolute(&program).map_err(|_| Error::Launch { errno: Errno::ENOENT })?;
+        }
+        let program = CString::new(program.as_os_str().as_bytes()).map_err(invalid)?;
+        let mut argv = vec![CString::new(self.program.as_str()).map_err(invalid)?];
+        for arg in &self.args {
+            argv.push(CString::new(arg.as_str()).map_err(invalid)?);
+        }
+        let envp = self.environment()
+            .into_iter()
+            .map(|(key, value)| CString::new([key.as_bytes(), b"=", value.as_bytes()].concat()).map_err(invalid))
+            .collect::<Result<Vec<_>>>()?;
+        let current_dir = self.current_dir
+            .as_ref()
+            .map(|dir| CString::new(OsStr::new(dir).as_bytes()).map_err(invalid))
+            .transpose()?;
+        Ok(ExecArgs {
+            program,
+            argv,
+            envp,
+            current_dir,
+            disable_randomization: self.disable_randomization,
+            rlimits: self.get_rlimits().collect(),
+        })
+    }
+
+    /// Opens whatever the standard streams are redirected to, before forking so failures are
+    /// reported properly.
+    pub(crate) fn open_stdio(&self) -> Result<OpenStdio> {
+        let mut terminal = None;
+        if [&self.stdin, &self.stdout, &self.stderr].contains(&&Stdio::Pty) {
+            let pty = openpty(None, None).map_err(Error::os("openpty"))?;
+            // neither end should leak into the program, the slave gets dup'ed onto 0-2 which clears the flag
+            fcntl(&pty.master, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).map_err(Error::os("fcntl"))?;
+            fcntl(&pty.slave, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).map_err(Error::os("fcntl"))?;
+            terminal = Some((File::from(pty.master), pty.slave));
+        }
+        let open = |stdio: &Stdio, write: bool| -> Result<Option<OwnedFd>> {
+            let path = match stdio {
+                Stdio::Inherit => return Ok(None),
+                Stdio::Pty => {
+                    let (_, slave) = terminal.as_ref().unwrap();
+                    return slave.try_clone().map(Some).map_err(|e| Error::os("dup")(io_errno(&e)));
+                }
+                Stdio::Null => Path::new("/dev/null"),
+                Stdio::File(path) => path.as_path(),
+            };
+            // std opens everything close-on-exec
+            OpenOptions::new()
+                .read(!write)
+                .write(write)
+                .create(write)
+                .truncate(write)
+                .open(path)
+                .map(|file| Some(OwnedFd::from(file)))
+                .map_err(|e| Error::Redirect { path: path.to_path_buf(), errno: io_errno(&e) })
+        };
+        let streams = [open(&self.stdin, false)?, open(&self.stdout, true)?, open(&self.stderr, true)?];
+        Ok(OpenStdio { streams, terminal })
+    }
+}
+
+fn io_errno(error: &std::io::Error) -> Errno {
+    Errno::from_raw(error.raw_os_error().unwrap_or(0))
+}
+
+pub(crate) struct OpenStdio {
+    /// What ends up as fd 0, 1 and 2 of the inferior, `None` leaves ours in place.
+    pub streams: [Option<OwnedFd>; 3],
+    /// Master and slave end of the pseudo-terminal, if any stream uses one.
+    pub terminal: Option<(File, OwnedFd)>,
+}
+
+pub(crate) struct ExecArgs {
+    pub program: CString,
+    pub argv: Vec<CString>,
+    pub envp: Vec<CString>,
+    pub current_dir: Option<CString>,
+    pub disable_randomization: bool,
+    pub rlimits: Vec<(Resource, u64, u64)>,
+}
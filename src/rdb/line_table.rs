@@ -0,0 +1,137 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use gimli::{ColumnType, Dwarf, DwarfSections, EndianSlice, FileEntry, LineProgramHeader, LittleEndian, Unit};
+use crate::error::{Error, Result};
+use crate::rdb::elf::Elf;
+use crate::rdb::interval_index::IntervalIndex;
+
+/// The DWARF sections of an object as gimli reads them.
+pub(crate) type DwarfSlice<'a> = EndianSlice<'a, LittleEndian>;
+
+/// Reads the debug sections of `elf`, inflating compressed ones. Missing sections are empty.
+pub(crate) fn load_dwarf_sections(elf: &Elf) -> Result<DwarfSections<Cow<'_, [u8]>>> {
+    DwarfSections::load(|id| -> Result<Cow<'_, [u8]>> {
+        Ok(elf.section_data(id.name())?.unwrap_or(Cow::Borrowed(&[])))
+    })
+}
+
+pub(crate) fn dwarf_error(elf: &Elf) -> impl Fn(gimli::Error) -> Error + '_ {
+    |e| Error::Elf { path: elf.path().to_path_buf(), message: format!("Invalid DWARF: {}", e) }
+}
+
+/// The path of a line program file entry, relative ones made absolute with the compile unit's
+/// directory.
+pub(crate) fn file_path(
+    dwarf: &Dwarf<DwarfSlice<'_>>,
+    unit: &Unit<DwarfSlice<'_>>,
+    header: &LineProgramHeader<DwarfSlice<'_>>,
+    file: Option<&FileEntry<DwarfSlice<'_>>>,
+) -> gimli::Result<PathBuf> {
+    let mut path = unit.comp_dir.map(|dir| PathBuf::from(dir.to_string_lossy().as_ref())).unwrap_or_default();
+    if let Some(file) = file {
+        // absolute parts replace what came before
+        if let Some(dir) = file.directory(header) {
+            path.push(dwarf.attr_string(unit, dir)?.to_string_lossy().as_ref());
+        }
+        path.push(dwarf.attr_string(unit, file.path_name())?.to_string_lossy().as_ref());
+    }
+    Ok(path)
+}
+
+/// One row of a DWARF line table: where in the source the instructions from `address` up to
+/// the next row come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEntry {
+    /// The link time address, like [`Symbol::value`](crate::rdb::elf::Symbol::value).
+    pub address: u64,
+    pub file: Rc<Path>,
+    pub line: u64,
+    /// 0 when the compiler did not say.
+    pub column: u64,
+    /// A good place for a breakpoint on the line, the start of a statement.
+    pub is_stmt: bool,
+}
+
+/// The `.debug_line` programs of every compile unit of an object, run into rows. DWARF 2 to 5
+/// are supported.
+pub struct LineTable {
+    /// Row order within each sequence is kept, sequences follow each other.
+    entries: Vec<LineEntry>,
+    /// Indices into `entries` by the address range each row covers.
+    entries_by_address: IntervalIndex<usize>,
+}
+
+impl LineTable {
+    /// Runs the line programs of `elf`. File names are made absolute with the compile unit's
+    /// directory.
+    pub fn parse(elf: &Elf) -> Result<LineTable> {
+        let error = dwarf_error(elf);
+        let sections = load_dwarf_sections(elf)?;
+        let dwarf = sections.borrow(|section| EndianSlice::new(section, LittleEndian));
+
+        let mut entries: Vec<LineEntry> = Vec::new();
+        let mut ranges = Vec::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next().map_err(&error)? {
+            let unit = dwarf.unit(header).map_err(&error)?;
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+            let mut files: HashMap<u64, Rc<Path>> = HashMap::new();
+            // the row that starts the range the next row ends
+            let mut previous: Option<usize> = None;
+            let mut sequence_start = true;
+            let mut discarded = false;
+            let mut rows = program.rows();
+            while let Some((header, row)) = rows.next_row().map_err(&error)? {
+                if let Some(index) = previous.take() {
+                    ranges.push((entries[index].address..row.address(), index));
+                }
+                if sequence_start {
+                    // sequences of functions the linker threw away are moved to address 0
+                    discarded = row.address() == 0;
+                    sequence_start = false;
+                }
+                if row.end_sequence() {
+                    sequence_start = true;
+                    continue;
+                }
+                if discarded {
+                    continue;
+                }
+                let file = match files.get(&row.file_index()) {
+                    Some(file) => file.clone(),
+                    None => {
+                        let file: Rc<Path> = file_path(&dwarf, &unit, header, row.file(header)).map_err(&error)?.into();
+                        files.insert(row.file_index(), file.clone());
+                        file
+                    }
+                };
+                previous = Some(entries.len());
+                entries.push(LineEntry {
+                    address: row.address(),
+                    file,
+                    line: row.line().map_or(0, |line| line.get()),
+                    column: match row.column() {
+                        ColumnType::LeftEdge => 0,
+                        ColumnType::Column(column) => column.get(),
+                    },
+                    is_stmt: row.is_stmt(),
+                });
+            }
+        }
+        Ok(LineTable { entries, entries_by_address: IntervalIndex::new(ranges) })
+    }
+
+    pub fn entries(&self) -> &[LineEntry] {
+        &self.entries
+    }
+
+    /// The row covering the link time address `address`. Where several rows start at the same
+    /// address, the last one.
+    pub fn entry_at(&self, address: u64) -> Option<&LineEntry> {
+        self.entries_by_address.find(address).map(|index| &self.entries[*index])
+    }
+}
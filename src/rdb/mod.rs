@@ -1,2 +1,19 @@
+pub mod address;
+pub mod attach_diagnosis;
+pub mod breakpoint_site;
+pub mod call_frame;
+pub mod debug_file;
+pub mod disassembler;
+pub mod elf;
+pub mod function_table;
+pub mod interval_index;
+pub mod launch_options;
+pub mod line_table;
 pub mod process;
-mod register_info;
\ No newline at end of file
+pub mod register_info;
+pub mod register_value;
+pub mod registers;
+pub mod stop_reason;
+pub mod stoppoint_collection;
+pub mod thread;
+pub mod watchpoint;
\ No newline at end of file
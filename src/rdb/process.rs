@@ -1,46 +1,162 @@
-use std::ffi::CString;
-use std::process;
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::IoSliceMut;
+use std::path::Path;
+use std::rc::Rc;
+use std::os::fd::{AsRawFd, OwnedFd};
 use nix::errno::Errno;
 use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+use nix::libc;
+use nix::sys::personality::{self, Persona};
 use nix::sys::ptrace;
+use nix::sys::resource::setrlimit;
 use nix::sys::signal::{kill, Signal};
-use nix::sys::wait::{waitpid, WaitStatus};
-use nix::unistd::{close, execvp, fork, pipe, read, write, ForkResult, Pid};
+use nix::sys::uio::{process_vm_readv, RemoteIoVec};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{chdir, close, execvpe, fork, setsid, gettid, pipe, read, write, ForkResult, Pid};
+use crate::error::{Error, Result};
+use crate::rdb::address::{FileAddr, VirtAddr};
+use crate::rdb::attach_diagnosis;
+use crate::rdb::breakpoint_site::{BreakpointSite, INT3};
+use crate::rdb::call_frame::return_address_rule;
+use crate::rdb::disassembler::call_return_address;
+use crate::rdb::elf::{Elf, SymbolLocation};
+use crate::rdb::function_table::Function;
+use crate::rdb::launch_options::{ExecArgs, LaunchOptions, OpenStdio, StopAt};
+use crate::rdb::line_table::LineEntry;
+use crate::rdb::register_info::RegisterId;
+use crate::rdb::registers::Registers;
+use crate::rdb::stop_reason::{StopInfo, StopReason, TrapKind};
+use crate::rdb::stoppoint_collection::{Stoppoint, StoppointCollection};
+use crate::rdb::thread::Thread;
+use crate::rdb::watchpoint::{StoppointMode, Watchpoint};
 
+const PAGE_SIZE: u64 = 0x1000;
+/// A launching child reports to the parent in records of a kind byte and an errno.
+const LAUNCH_REPORT_SIZE: usize = 5;
+/// The launch failed, the errno of what failed follows.
+const LAUNCH_REPORT_FAILED: u8 = 0;
+/// Disabling address space randomization failed, the program is launched anyway.
+const LAUNCH_REPORT_PERSONALITY: u8 = 1;
+/// What a source step that got where it was going reports.
+const STEP_DONE: StopReason = StopReason { state: ProcessState::Stopped, info: StopInfo::Trap(TrapKind::SingleStep) };
+/// Auxiliary vector key holding the runtime address of the program's entry point.
+const AT_ENTRY: u64 = 9;
+/// How often running threads are polled while more than one of them runs.
+const THREAD_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1);
+const DEBUG_ADDRESS_REGISTERS: [RegisterId; 4] = [RegisterId::Dr0, RegisterId::Dr1, RegisterId::Dr2, RegisterId::Dr3];
+/// New threads and processes are traced from their first instruction, execs are reported.
+const TRACE_OPTIONS: ptrace::Options = ptrace::Options::PTRACE_O_TRACECLONE
+    .union(ptrace::Options::PTRACE_O_TRACEFORK)
+    .union(ptrace::Options::PTRACE_O_TRACEVFORK)
+    .union(ptrace::Options::PTRACE_O_TRACEVFORKDONE)
+    .union(ptrace::Options::PTRACE_O_TRACEEXEC);
 
 pub struct Process {
     pid: Pid,
     terminate_on_end: bool,
-    pub process_state: ProcessState
+    pub process_state: ProcessState,
+    /// Every thread we trace, the thread group leader first.
+    threads: Vec<Thread>,
+    /// The thread registers, the pc and stepping refer to, by default the one that last stopped.
+    current_tid: Pid,
+    next_thread_id: usize,
+    /// The thread being single stepped, whatever interrupts the step has to step again.
+    stepping_tid: Option<Pid>,
+    /// How many of the functions inlined at the pc we count as not entered yet. The pc is at
+    /// the start of their code, as far as source stepping goes we are still at the call.
+    inline_height: usize,
+    /// [`TRACE_OPTIONS`] are in place, launched processes only get them at their first stop.
+    options_set: bool,
+    follow_fork_mode: FollowForkMode,
+    detach_on_fork: bool,
+    /// The side of the last fork we did not follow, when it was not detached from.
+    unfollowed: Option<Box<Process>>,
+    /// Software breakpoints taken out of memory a vfork parent and child share, they go back
+    /// in once the memory is ours alone again: at VFORK_DONE in the parent, at exec in the child.
+    vfork_lifted: Vec<i32>,
+    breakpoint_sites: StoppointCollection<BreakpointSite>,
+    watchpoints: StoppointCollection<Watchpoint>,
+    launch_options: Option<LaunchOptions>,
+    /// The executable running in the process, `None` if it could not be read.
+    elf: Option<Rc<Elf>>,
+    /// A launch is through exec before the kernel has filled in the auxiliary vector, the
+    /// executable is loaded at the first stop instead.
+    elf_pending: bool,
+    terminal: Option<File>,
+    /// Where to run to once the exec trap of a fresh launch has been collected.
+    pending_stop_at: Option<StopAt>,
+    /// The process was in group-stop before we attached, detaching leaves it that way.
+    originally_stopped: bool,
+    /// Attached with PTRACE_SEIZE, so it can be stopped with PTRACE_INTERRUPT instead of SIGSTOP.
+    is_seized: bool,
+    /// Why the launch could not disable address space randomization as it was asked to.
+    randomization_error: Option<Errno>,
 }
 
-#[derive(Copy, Clone)]
+/// What a wait status meant for the thread it came from.
+enum ThreadEvent {
+    /// A stop or exit the caller has to hear about.
+    Report(StopReason),
+    /// The stop we asked for with SIGSTOP or PTRACE_INTERRUPT, the thread is now stopped.
+    Requested,
+    /// Dealt with already, a new thread or one that ended while the process goes on.
+    Handled,
+}
+
+/// Which process the debugger stays with after the inferior forks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FollowForkMode {
+    #[default]
+    Parent,
+    Child,
+}
+
+impl FollowForkMode {
+    pub fn parse(text: &str) -> Result<Self> {
+        match text {
+            "parent" => Ok(FollowForkMode::Parent),
+            "child" => Ok(FollowForkMode::Child),
+            _ => Err(Error::Parse(format!("Invalid follow-fork-mode, expected parent or child: {}", text))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FollowForkMode::Parent => "parent",
+            FollowForkMode::Child => "child",
+        }
+    }
+}
+
+/// The stoppoint that owns the debug register reported in DR6.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HardwareStoppoint {
+    BreakpointSite(i32),
+    Watchpoint(i32)
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ProcessState {
     Stopped,
     Running,
     Exited,
-    Terminated
+    Terminated,
+    /// We let go of it, it runs (or stays stopped) on its own.
+    Detached
 }
 
 impl Drop for Process{
+    /// Kills processes we launched and detaches from ones we attached to, nothing is left to do
+    /// for a process that already exited, was killed or was detached.
     fn drop(&mut self) {
-        println!("Dropping: {}", self.pid);
-        if let ProcessState::Running = self.process_state {
-            if let Err(e) = kill(self.pid, Signal::SIGSTOP) {
-                eprintln!("Failed to stop process {}: {}", self.pid, e);
+        match self.process_state {
+            ProcessState::Exited | ProcessState::Terminated | ProcessState::Detached => {}
+            _ if self.terminate_on_end => {
+                let _ = self.kill();
             }
-        }
-        ptrace::detach(self.pid, None);
-        kill(self.pid, Signal::SIGCONT);
-        if !self.terminate_on_end {
-            println!("Not killing: {}", self.pid);
-        }
-        if self.terminate_on_end {
-            println!("killing process: {}", self.pid);
-            if let Err(e) = kill(self.pid, Signal::SIGKILL){
-                eprintln!("Failed to kill process {}: {}", self.pid, e);
-            }else{
-                waitpid(self.pid, None);
+            _ => {
+                let _ = self.detach();
             }
         }
     }
@@ -51,34 +167,611 @@ impl Process {
         Self {
             pid,
             terminate_on_end,
-            process_state
+            process_state,
+            threads: vec![Thread::new(1, pid)],
+            current_tid: pid,
+            next_thread_id: 2,
+            stepping_tid: None,
+            inline_height: 0,
+            options_set: false,
+            follow_fork_mode: FollowForkMode::default(),
+            detach_on_fork: true,
+            unfollowed: None,
+            vfork_lifted: Vec::new(),
+            breakpoint_sites: StoppointCollection::default(),
+            watchpoints: StoppointCollection::default(),
+            launch_options: None,
+            elf: None,
+            elf_pending: false,
+            terminal: None,
+            pending_stop_at: None,
+            originally_stopped: false,
+            is_seized: false,
+            randomization_error: None,
         }
     }
     pub fn pid(&self) ->Pid{
         self.pid
     }
-    pub fn attach(pid_arg: &str) -> Result<Process, String> {
+    /// Registers of the current thread.
+    pub fn registers(&self) -> &Registers {
+        &self.current_thread().registers
+    }
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        let tid = self.current_tid;
+        &mut self.thread_mut(tid).unwrap().registers
+    }
+    pub fn get_pc(&self) -> VirtAddr {
+        self.current_thread().get_pc()
+    }
+    pub fn set_pc(&mut self, pc: VirtAddr) -> Result<()> {
+        self.registers_mut().write_by_id(RegisterId::Rip, pc.addr())
+    }
+    pub fn threads(&self) -> &[Thread] {
+        &self.threads
+    }
+    pub fn current_thread(&self) -> &Thread {
+        self.thread(self.current_tid).unwrap()
+    }
+    /// Makes the thread with this [`Thread::id`] the one registers and stepping refer to.
+    pub fn select_thread(&mut self, id: usize) -> Result<()> {
+        let thread = self.threads.iter().find(|t| t.id() == id).ok_or(Error::NoSuchThread(id))?;
+        self.current_tid = thread.tid();
+        self.update_inline_height();
+        Ok(())
+    }
+    fn thread(&self, tid: Pid) -> Option<&Thread> {
+        self.threads.iter().find(|t| t.tid() == tid)
+    }
+    fn thread_mut(&mut self, tid: Pid) -> Option<&mut Thread> {
+        self.threads.iter_mut().find(|t| t.tid() == tid)
+    }
+    /// Starts tracking a thread that starts out running, adding a known thread does nothing.
+    fn add_thread(&mut self, tid: Pid) -> &mut Thread {
+        if self.thread(tid).is_none() {
+            self.threads.push(Thread::new(self.next_thread_id, tid));
+            self.next_thread_id += 1;
+        }
+        self.thread_mut(tid).unwrap()
+    }
+    pub fn breakpoint_sites(&self) -> &StoppointCollection<BreakpointSite> {
+        &self.breakpoint_sites
+    }
+    pub fn watchpoints(&self) -> &StoppointCollection<Watchpoint> {
+        &self.watchpoints
+    }
+    /// The executable the process runs, as the kernel found it: PATH lookups and symlinks are
+    /// resolved and an exec swaps it for the new program.
+    pub fn elf(&self) -> Option<&Elf> {
+        self.elf.as_deref()
+    }
+    pub(crate) fn shared_elf(&self) -> Option<Rc<Elf>> {
+        self.elf.clone()
+    }
+    /// Maps the executable through `/proc/pid/exe`, which still opens the file that is running
+    /// if it was replaced or deleted on disk since. The load bias is how far AT_ENTRY is from
+    /// the entry point the executable was linked for.
+    fn load_elf(&mut self) {
+        self.elf = Elf::open(format!("/proc/{}/exe", self.pid.as_raw())).ok().map(|mut elf| {
+            if let Ok(entry) = auxv_entry(self.pid, AT_ENTRY) {
+                elf.set_load_bias(entry.wrapping_sub(elf.entry().addr()));
+            }
+            Rc::new(elf)
+        });
+    }
+    /// The function or object of the executable `address` is in, e.g. `main+0x20`.
+    pub fn symbolize(&self, address: VirtAddr) -> Option<SymbolLocation<'_>> {
+        self.elf()?.symbol_at_virt_addr(address)
+    }
+    /// The source line `address` belongs to, from the executable's line table.
+    pub fn line_entry_at(&self, address: VirtAddr) -> Option<&LineEntry> {
+        let elf = self.elf()?;
+        elf.line_entry_at(address.to_file_addr(elf)?)
+    }
+    /// The functions from the executable's debug information the pc is in, innermost first:
+    /// inlined copies down to the function with its own code. Inlined functions the pc is at
+    /// the very start of are left out while [`Process::inline_height`] counts them.
+    pub fn function_frames(&self) -> Vec<&Function> {
+        let mut frames = self.function_frames_at(self.get_pc());
+        frames.drain(..self.inline_height.min(frames.len()));
+        frames
+    }
+    fn function_frames_at(&self, address: VirtAddr) -> Vec<&Function> {
+        let Some(elf) = self.elf() else {
+            return Vec::new();
+        };
+        address.to_file_addr(elf).map(|address| elf.function_frames_at(address)).unwrap_or_default()
+    }
+    /// How many functions inlined right at the pc have not been stepped into yet.
+    pub fn inline_height(&self) -> usize {
+        self.inline_height
+    }
+    /// The source file and line the current thread is at. That is the line of the pc, or the
+    /// call of the outermost inlined function not stepped into yet.
+    pub fn source_location(&self) -> Option<(Rc<Path>, u64)> {
+        if self.inline_height > 0 {
+            return self.function_frames_at(self.get_pc()).get(self.inline_height - 1)?.call_site.clone();
+        }
+        self.line_entry_at(self.get_pc()).map(|entry| (entry.file.clone(), entry.line))
+    }
+    /// A stop at the start of inlined code counts as a stop at the call, stepping in goes into
+    /// the inlined functions one by one.
+    fn update_inline_height(&mut self) {
+        let pc = self.get_pc();
+        let Some(elf) = self.elf() else {
+            self.inline_height = 0;
+            return;
+        };
+        let starts_here = |f: &&&Function| f.is_inlined() && FileAddr::new(elf, f.entry).to_virt_addr() == pc;
+        self.inline_height = self.function_frames_at(pc).iter().take_while(starts_here).count();
+    }
+    /// The runtime address of the symbol called `name`, mangled or demangled. Functions are
+    /// preferred over data of the same name.
+    pub fn lookup_symbol(&self, name: &str) -> Result<VirtAddr> {
+        let elf = self.elf().ok_or_else(|| Error::NoSuchSymbol(name.to_string()))?;
+        elf.function(name)
+            .or_else(|| elf.symbols_by_name(name).find(|s| s.is_defined() && s.value != 0))
+            .map(|symbol| FileAddr::new(elf, symbol.value).to_virt_addr())
+            .ok_or_else(|| Error::NoSuchSymbol(name.to_string()))
+    }
+    /// Master end of the pseudo-terminal the process was given with `Stdio::Pty`. Reading it
+    /// returns what the program wrote, once every copy of the slave end is closed reads fail
+    /// with EIO. Keep reading while the process runs, a full terminal blocks its writes.
+    pub fn terminal(&self) -> Option<&File> {
+        self.terminal.as_ref()
+    }
+    /// How the process was started, `None` for attached processes.
+    pub fn launch_options(&self) -> Option<&LaunchOptions> {
+        self.launch_options.as_ref()
+    }
+    /// Changes take effect the next time the program is launched from these options.
+    pub fn launch_options_mut(&mut self) -> Option<&mut LaunchOptions> {
+        self.launch_options.as_mut()
+    }
+    pub fn follow_fork_mode(&self) -> FollowForkMode {
+        self.follow_fork_mode
+    }
+    pub fn set_follow_fork_mode(&mut self, mode: FollowForkMode) {
+        self.follow_fork_mode = mode;
+    }
+    /// Whether the side of a fork we do not follow is let go, on by default like in GDB.
+    pub fn detach_on_fork(&self) -> bool {
+        self.detach_on_fork
+    }
+    pub fn set_detach_on_fork(&mut self, detach: bool) {
+        self.detach_on_fork = detach;
+    }
+    /// Hands out the process left behind by the last fork when detach-on-fork is off. It stays
+    /// stopped at the fork until resumed through the returned `Process`.
+    pub fn take_unfollowed(&mut self) -> Option<Process> {
+        self.unfollowed.take().map(|process| *process)
+    }
+    /// Why disabling address space randomization failed when the process was launched, `None`
+    /// if it worked, was not asked for or the process was attached to.
+    pub fn randomization_error(&self) -> Option<Errno> {
+        self.randomization_error
+    }
+    /// Whether the process runs with `ADDR_NO_RANDOMIZE`, read from `/proc/pid/personality`.
+    pub fn is_randomization_disabled(&self) -> Result<bool> {
+        let persona = std::fs::read_to_string(format!("/proc/{}/personality", self.pid.as_raw()))
+            .map_err(|e| Error::os("read personality")(Errno::from_raw(e.raw_os_error().unwrap_or(0))))?;
+        let persona = u64::from_str_radix(persona.trim(), 16)
+            .map_err(|_| Error::os("read personality")(Errno::EINVAL))?;
+        Ok(persona & Persona::ADDR_NO_RANDOMIZE.bits() as u64 != 0)
+    }
+    /// Creates a disabled breakpoint site at `address` and returns its id.
+    /// Hardware sites use a debug register instead of patching memory.
+    pub fn create_breakpoint_site(&mut self, address: VirtAddr, hardware: bool) -> Result<i32> {
+        if self.breakpoint_sites.contains_address(address) {
+            return Err(Error::Stoppoint(format!("Breakpoint site already created at address {:#x}", address)));
+        }
+        let id = self.breakpoint_sites.next_id();
+        self.breakpoint_sites.push(BreakpointSite::new(id, self.pid, address, hardware, false));
+        Ok(id)
+    }
+    pub fn enable_breakpoint_site(&mut self, id: i32) -> Result<()> {
+        let site = self.breakpoint_sites.get_by_id_mut(id)
+            .ok_or_else(|| Error::Stoppoint(format!("No breakpoint with id {}", id)))?;
+        if !site.is_hardware() {
+            return site.enable();
+        }
+        if site.is_enabled() {
+            return Ok(());
+        }
+        let address = site.address();
+        let slot = self.set_hardware_stoppoint(address, StoppointMode::Execute, 1)?;
+        self.breakpoint_sites.get_by_id_mut(id).unwrap().set_hardware_slot(Some(slot));
+        Ok(())
+    }
+    pub fn disable_breakpoint_site(&mut self, id: i32) -> Result<()> {
+        let site = self.breakpoint_sites.get_by_id_mut(id)
+            .ok_or_else(|| Error::Stoppoint(format!("No breakpoint with id {}", id)))?;
+        if !site.is_hardware() {
+            return site.disable();
+        }
+        if let Some(slot) = site.hardware_slot() {
+            self.clear_hardware_stoppoint(slot)?;
+            self.breakpoint_sites.get_by_id_mut(id).unwrap().set_hardware_slot(None);
+        }
+        Ok(())
+    }
+    /// Restores the original byte and forgets the site.
+    pub fn remove_breakpoint_site(&mut self, id: i32) -> Result<()> {
+        self.disable_breakpoint_site(id)?;
+        self.breakpoint_sites.remove_by_id(id);
+        Ok(())
+    }
+    /// Creates a disabled watchpoint, `address` has to be aligned to `size` (1, 2, 4 or 8).
+    pub fn create_watchpoint(&mut self, address: VirtAddr, mode: StoppointMode, size: usize) -> Result<i32> {
+        if self.watchpoints.contains_address(address) {
+            return Err(Error::Stoppoint(format!("Watchpoint already created at address {:#x}", address)));
+        }
+        let id = self.watchpoints.next_id();
+        let mut watchpoint = Watchpoint::new(id, address, mode, size)?;
+        watchpoint.update_data(self.read_watched_value(address, size)?);
+        self.watchpoints.push(watchpoint);
+        Ok(id)
+    }
+    pub fn enable_watchpoint(&mut self, id: i32) -> Result<()> {
+        let watchpoint = self.watchpoints.get_by_id(id)
+            .ok_or_else(|| Error::Stoppoint(format!("No watchpoint with id {}", id)))?;
+        if watchpoint.is_enabled() {
+            return Ok(());
+        }
+        let (address, mode, size) = (watchpoint.address(), watchpoint.mode(), watchpoint.size());
+        let slot = self.set_hardware_stoppoint(address, mode, size)?;
+        self.watchpoints.get_by_id_mut(id).unwrap().set_hardware_slot(Some(slot));
+        Ok(())
+    }
+    pub fn disable_watchpoint(&mut self, id: i32) -> Result<()> {
+        let watchpoint = self.watchpoints.get_by_id(id)
+            .ok_or_else(|| Error::Stoppoint(format!("No watchpoint with id {}", id)))?;
+        if let Some(slot) = watchpoint.hardware_slot() {
+            self.clear_hardware_stoppoint(slot)?;
+            self.watchpoints.get_by_id_mut(id).unwrap().set_hardware_slot(None);
+        }
+        Ok(())
+    }
+    /// Frees the debug register and forgets the watchpoint.
+    pub fn remove_watchpoint(&mut self, id: i32) -> Result<()> {
+        self.disable_watchpoint(id)?;
+        self.watchpoints.remove_by_id(id);
+        Ok(())
+    }
+    /// Works out which hardware stoppoint, if any, caused the current SIGTRAP by looking at the
+    /// low four bits of DR6.
+    pub fn get_current_hardware_stoppoint(&self) -> Option<HardwareStoppoint> {
+        self.hardware_stoppoint_of(self.current_thread())
+    }
+    fn hardware_stoppoint_of(&self, thread: &Thread) -> Option<HardwareStoppoint> {
+        let status = thread.registers.read_by_id_as::<u64>(RegisterId::Dr6);
+        let slot = (0..4).find(|i| status & (1 << i) != 0)?;
+        if let Some(site) = self.breakpoint_sites.iter().find(|s| s.hardware_slot() == Some(slot)) {
+            return Some(HardwareStoppoint::BreakpointSite(site.id()));
+        }
+        self.watchpoints
+            .iter()
+            .find(|w| w.hardware_slot() == Some(slot))
+            .map(|w| HardwareStoppoint::Watchpoint(w.id()))
+    }
+    /// Claims a free debug register for `address` and arms it in DR7 of every thread, returns
+    /// the slot used.
+    fn set_hardware_stoppoint(&mut self, address: VirtAddr, mode: StoppointMode, size: usize) -> Result<usize> {
+        let control = self.registers().read_by_id_as::<u64>(RegisterId::Dr7);
+        // bit 2 * i is the local enable bit of DRi
+        let slot = (0..4)
+            .find(|i| control & (0b11 << (i * 2)) == 0)
+            .ok_or_else(|| Error::Stoppoint("No remaining hardware debug registers".to_string()))?;
+
+        let rw_bits: u64 = match mode {
+            StoppointMode::Execute => 0b00,
+            StoppointMode::Write => 0b01,
+            StoppointMode::ReadWrite => 0b11,
+        };
+        // execute breakpoints must use LEN 00, other lengths are undefined
+        if mode == StoppointMode::Execute && size != 1 {
+            return Err(Error::Stoppoint(format!("Invalid execute stoppoint size: {}", size)));
+        }
+        // 8 bytes is 0b10, the encoding is not in order
+        let len_bits: u64 = match size {
+            1 => 0b00,
+            2 => 0b01,
+            4 => 0b11,
+            8 => 0b10,
+            _ => return Err(Error::Stoppoint(format!("Invalid hardware stoppoint size: {}", size))),
+        };
+        let clear_mask = (0b11 << (slot * 2)) | (0b1111 << (slot * 4 + 16));
+        let enable = 0b01 << (slot * 2);
+        let condition = (rw_bits | len_bits << 2) << (slot * 4 + 16);
+        let control = (control & !clear_mask) | enable | condition;
+
+        self.write_debug_register(DEBUG_ADDRESS_REGISTERS[slot], address.addr())?;
+        self.write_debug_register(RegisterId::Dr7, control)?;
+        Ok(slot)
+    }
+    fn clear_hardware_stoppoint(&mut self, slot: usize) -> Result<()> {
+        let control = self.registers().read_by_id_as::<u64>(RegisterId::Dr7);
+        let clear_mask = (0b11 << (slot * 2)) | (0b1111 << (slot * 4 + 16));
+        self.write_debug_register(RegisterId::Dr7, control & !clear_mask)?;
+        self.write_debug_register(DEBUG_ADDRESS_REGISTERS[slot], 0)
+    }
+    /// Debug registers are per thread, all threads get the same hardware stoppoints. Threads
+    /// that cannot be written to yet catch up at their next stop.
+    fn write_debug_register(&mut self, id: RegisterId, value: u64) -> Result<()> {
+        for thread in &mut self.threads {
+            if thread.state == ProcessState::Stopped {
+                thread.registers.write_by_id(id, value)?;
+            } else {
+                thread.needs_debug_registers = true;
+            }
+        }
+        Ok(())
+    }
+    /// Gives `tid` the debug registers of a stopped thread that has them, new threads start
+    /// without any.
+    fn copy_debug_registers(&mut self, tid: Pid) -> Result<()> {
+        let source = self.threads.iter()
+            .find(|t| t.tid() != tid && t.state == ProcessState::Stopped && !t.needs_debug_registers)
+            .map(|t| &t.registers);
+        let values: Vec<(RegisterId, u64)> = match source {
+            Some(registers) => DEBUG_ADDRESS_REGISTERS
+                .iter()
+                .chain([&RegisterId::Dr7])
+                .map(|id| (*id, registers.read_by_id_as::<u64>(*id)))
+                .collect(),
+            None => Vec::new(),
+        };
+        let thread = self.thread_mut(tid).unwrap();
+        for (id, value) in values {
+            thread.registers.write_by_id(id, value)?;
+        }
+        thread.needs_debug_registers = false;
+        Ok(())
+    }
+    fn read_watched_value(&self, address: VirtAddr, size: usize) -> Result<u64> {
+        let mut bytes = [0u8; 8];
+        bytes[..size].copy_from_slice(&self.read_memory(address, size)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+    /// Reads `amount` bytes at `address`. Either everything is read or the error names the first
+    /// address that could not be, partial reads are never handed back.
+    pub fn read_memory(&self, address: VirtAddr, amount: usize) -> Result<Vec<u8>> {
+        let end = address.checked_add(amount as u64).ok_or(Error::MemoryAccess { addr: address })?.addr();
+        let mut memory = vec![0u8; amount];
+        // one remote iovec per page, process_vm_readv never splits an iovec so it stops exactly
+        // at the first page it cannot read
+        let mut remote = Vec::new();
+        let mut current = address.addr();
+        while current < end {
+            let next_page = (current | (PAGE_SIZE - 1)).saturating_add(1).min(end);
+            remote.push(RemoteIoVec { base: current as usize, len: (next_page - current) as usize });
+            current = next_page;
+        }
+        // process_vm_readv can be refused where ptrace is not (no CONFIG_CROSS_MEMORY_ATTACH,
+        // pages only ptrace may read), whatever it leaves over goes through PEEKDATA
+        let read = process_vm_readv(self.pid, &mut [IoSliceMut::new(&mut memory)], &remote).unwrap_or(0);
+        if read < amount {
+            self.peek_memory(address + read as u64, &mut memory[read..])?;
+        }
+        Ok(memory)
+    }
+    /// Same as [`Process::read_memory`] but with the original instruction bytes in place of the
+    /// `int3`s our enabled software breakpoints patched in.
+    pub fn read_memory_without_traps(&self, address: VirtAddr, amount: usize) -> Result<Vec<u8>> {
+        let mut memory = self.read_memory(address, amount)?;
+        for site in self.breakpoint_sites.iter().filter(|s| s.is_enabled() && !s.is_hardware()) {
+            if let Some(offset) = site.address().addr().checked_sub(address.addr())
+                && offset < amount as u64 {
+                memory[offset as usize] = site.saved_data();
+            }
+        }
+        Ok(memory)
+    }
+    /// Writes `data` at `address` with POKEDATA, which unlike process_vm_writev can patch
+    /// read-only text. A byte under an enabled software breakpoint becomes the site's saved byte
+    /// so the `int3` stays armed. A failure part way leaves the bytes before it written.
+    pub fn write_memory(&mut self, address: VirtAddr, data: &[u8]) -> Result<()> {
+        address.checked_add(data.len() as u64).ok_or(Error::MemoryAccess { addr: address })?;
+        let mut patched = data.to_vec();
+        for site in self.breakpoint_sites.iter().filter(|s| s.is_enabled() && !s.is_hardware()) {
+            if let Some(offset) = site.address().addr().checked_sub(address.addr())
+                && offset < data.len() as u64 {
+                patched[offset as usize] = INT3;
+            }
+        }
+        let mut written = 0;
+        let result = self.poke_memory(address, &patched, &mut written);
+        // only what made it into memory replaces the saved bytes
+        for site in self.breakpoint_sites.iter_mut().filter(|s| s.is_enabled() && !s.is_hardware()) {
+            if let Some(offset) = site.address().addr().checked_sub(address.addr())
+                && offset < written as u64 {
+                site.set_saved_data(data[offset as usize]);
+            }
+        }
+        result
+    }
+    /// Writes `data` at `address` one POKEDATA word at a time, counting the bytes done in
+    /// `written` so a failure tells how far it got.
+    fn poke_memory(&self, address: VirtAddr, data: &[u8], written: &mut usize) -> Result<()> {
+        while *written < data.len() {
+            let current = address.addr() + *written as u64;
+            let aligned = current & !0b111;
+            let offset = (current - aligned) as usize;
+            let count = (8 - offset).min(data.len() - *written);
+            // POKEDATA always writes a whole word, keep whatever we are not overwriting
+            let mut word = [0u8; 8];
+            if count < 8 {
+                self.peek_memory(VirtAddr::new(aligned), &mut word)?;
+            }
+            word[offset..offset + count].copy_from_slice(&data[*written..*written + count]);
+            ptrace::write(self.pid, aligned as ptrace::AddressType, i64::from_le_bytes(word))
+                .map_err(Error::memory(VirtAddr::new(current), self.pid))?;
+            *written += count;
+        }
+        Ok(())
+    }
+    /// Fills `buffer` from `address` one PEEKDATA word at a time.
+    fn peek_memory(&self, address: VirtAddr, buffer: &mut [u8]) -> Result<()> {
+        let mut read = 0;
+        while read < buffer.len() {
+            let current = address.addr() + read as u64;
+            // aligned words never straddle a page, so when a read fails it fails at `current`
+            let aligned = current & !0b111;
+            let word = ptrace::read(self.pid, aligned as ptrace::AddressType)
+                .map_err(Error::memory(VirtAddr::new(current), self.pid))?;
+            let offset = (current - aligned) as usize;
+            let count = (8 - offset).min(buffer.len() - read);
+            buffer[read..read + count].copy_from_slice(&word.to_le_bytes()[offset..offset + count]);
+            read += count;
+        }
+        Ok(())
+    }
+    /// Seizes every thread of `pid_arg` and interrupts them. The interrupt of the thread group
+    /// leader is the stop to collect with [`Process::wait_on_signal`], the others are gathered
+    /// along with it.
+    pub fn attach(pid_arg: &str) -> Result<Process> {
         let pid = pid_arg
             .parse::<i32>()
-            .map_err(|_|"Invalid PID: not a valid number")?;
+            .ok()
+            .filter(|pid| *pid > 0)
+            .ok_or_else(|| Error::InvalidPid(pid_arg.to_string()))?;
 
-        if pid <= 0 {
-            return Err("Invalid PID: must be positive".to_string())
-        }
+        // has to be looked at before attaching, afterwards the process is stopped either way
+        let originally_stopped = is_group_stopped(Pid::from_raw(pid));
 
-        ptrace::attach(Pid::from_raw(pid))
-            .map_err(|e| format!("Failed to attach: {}", e))?;
+        // unlike PTRACE_ATTACH, seizing sends no SIGSTOP the program could see or swallow
+        ptrace::seize(Pid::from_raw(pid), TRACE_OPTIONS)
+            .map_err(|errno| attach_diagnosis::diagnose(pid, errno))?;
 
         let process_state = ProcessState::Running;
 
         let terminate_on_end = false;
 
-        let process = Process::new(Pid::from_raw(pid), terminate_on_end, process_state);
+        let mut process = Process::new(Pid::from_raw(pid), terminate_on_end, process_state);
+        process.originally_stopped = originally_stopped;
+        process.is_seized = true;
+        process.options_set = true;
+        process.load_elf();
+
+        ptrace::interrupt(process.pid).map_err(Error::ptrace("PTRACE_INTERRUPT", process.pid))?;
+
+        // threads can start while we go through the others, look again until no new ones show up
+        loop {
+            let mut seized_any = false;
+            for tid in thread_ids(process.pid) {
+                if process.thread(tid).is_some() {
+                    continue;
+                }
+                match ptrace::seize(tid, TRACE_OPTIONS) {
+                    Ok(()) => {
+                        ptrace::interrupt(tid).map_err(Error::ptrace("PTRACE_INTERRUPT", tid))?;
+                    }
+                    // gone again already
+                    Err(Errno::ESRCH) => continue,
+                    // cloned by a thread we seized, it is ours already and comes with its own stop
+                    Err(Errno::EPERM) if is_traced_by_us(tid) => {}
+                    Err(errno) => return Err(Error::ptrace("PTRACE_SEIZE", tid)(errno)),
+                }
+                process.add_thread(tid).stop_requested = true;
+                seized_any = true;
+            }
+            if !seized_any {
+                break;
+            }
+        }
 
         Ok(process)
     }
-    pub fn launch(program_path: &str) -> Result<Process, String>{
-        let (read_fd, write_fd) = pipe().map_err(|e|format!("pipe failed: {}", e))?;
+    /// Removes our breakpoints and watchpoints and stops tracing the process. It carries on
+    /// running, or stays stopped if it was stopped when we attached. A running process is
+    /// stopped first, signals its threads stop for on the way are passed on to them.
+    pub fn detach(&mut self) -> Result<()> {
+        self.ensure_alive()?;
+        // only the tracing thread can stop and wait for the process
+        if self.process_state == ProcessState::Running && is_traced_by_us(self.pid) {
+            self.stop_all_threads()?;
+            if self.process_state != ProcessState::Running {
+                // it exited while we were stopping it
+                return Ok(());
+            }
+            self.process_state = ProcessState::Stopped;
+        }
+        // a process we leave running must not trip over our int3s or debug registers once we are gone
+        let site_ids: Vec<i32> = self.breakpoint_sites.iter().map(|s| s.id()).collect();
+        for id in site_ids {
+            self.disable_breakpoint_site(id)?;
+        }
+        let watchpoint_ids: Vec<i32> = self.watchpoints.iter().map(|w| w.id()).collect();
+        for id in watchpoint_ids {
+            self.disable_watchpoint(id)?;
+        }
+        for thread in &mut self.threads {
+            let signal = match thread.pending_signal() {
+                _ if thread.tid() == self.pid && self.originally_stopped => Some(Signal::SIGSTOP),
+                Some(signal) if thread.pending => Some(signal),
+                _ => None,
+            };
+            match ptrace::detach(thread.tid(), signal) {
+                // a thread that ended in the meantime
+                Err(Errno::ESRCH) if thread.tid() != self.pid => {}
+                result => result.map_err(Error::ptrace("PTRACE_DETACH", thread.tid()))?,
+            }
+            thread.state = ProcessState::Detached;
+        }
+        if !self.originally_stopped {
+            // a SIGSTOP we sent to stop a thread can still be queued, it must not stop the process
+            let _ = kill(self.pid, Signal::SIGCONT);
+        }
+        self.process_state = ProcessState::Detached;
+        Ok(())
+    }
+    /// Sends SIGKILL and reaps the process.
+    pub fn kill(&mut self) -> Result<()> {
+        self.ensure_alive()?;
+        kill(self.pid, Signal::SIGKILL).map_err(|e| match e {
+            Errno::ESRCH => Error::NoSuchProcess(self.pid.as_raw()),
+            e => Error::os("kill")(e),
+        })?;
+        self.process_state = ProcessState::Terminated;
+        // every traced thread has to be reaped, the leader is only reported once the others are
+        let mut tids: Vec<Pid> = self.threads.iter().map(|t| t.tid()).collect();
+        tids.sort_by_key(|tid| *tid == self.pid);
+        for tid in tids {
+            // a tracee can still report stops it had queued before it dies
+            loop {
+                match waitpid(tid, Some(WaitPidFlag::__WALL)) {
+                    Ok(WaitStatus::Exited(..) | WaitStatus::Signaled(..)) | Err(Errno::ECHILD) => break,
+                    Ok(_) => {}
+                    Err(e) => return Err(Error::os("waitpid")(e)),
+                }
+            }
+        }
+        for thread in &mut self.threads {
+            thread.state = ProcessState::Terminated;
+        }
+        Ok(())
+    }
+    fn ensure_alive(&self) -> Result<()> {
+        match self.process_state {
+            ProcessState::Exited | ProcessState::Terminated | ProcessState::Detached => {
+                Err(Error::InvalidState { pid: self.pid.as_raw(), state: self.process_state })
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Launches `program_path` without arguments, see [`Process::launch_with_options`].
+    pub fn launch(program_path: &str) -> Result<Process> {
+        Self::launch_with_options(&LaunchOptions::new(program_path))
+    }
+    /// Forks and execs the inferior under ptrace, the first stop still has to be collected with
+    /// [`Process::wait_on_signal`]. That is the exec SIGTRAP, or the entry point or `main` when
+    /// the options ask for it.
+    pub fn launch_with_options(options: &LaunchOptions) -> Result<Process> {
+        // built before forking, the child should do as little as possible before exec
+        let exec_args = options.to_exec_args()?;
+        let stdio = options.open_stdio()?;
+
+        let (read_fd, write_fd) = pipe().map_err(Error::os("pipe"))?;
 
         fcntl(&read_fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).ok();
 
@@ -90,90 +783,876 @@ impl Process {
                 Ok(ForkResult::Parent {child}) => {
                     close(write_fd).ok(); //  we only want to read from the parent
 
-                    let mut buffer:[u8;256] = [0;256];
-
-                    let bytes_read = read(&read_fd, &mut buffer).unwrap_or(0);
+                    // the child reports errors as they happen, exec succeeding closes the pipe
+                    let mut randomization_error = None;
+                    let mut launch_error = None;
+                    let mut buffer = [0u8; LAUNCH_REPORT_SIZE];
+                    while read(&read_fd, &mut buffer).unwrap_or(0) == buffer.len() {
+                        let errno = Errno::from_raw(i32::from_ne_bytes(buffer[1..].try_into().unwrap()));
+                        match buffer[0] {
+                            LAUNCH_REPORT_PERSONALITY => randomization_error = Some(errno),
+                            // LAUNCH_REPORT_FAILED, the child exits right after so nothing else follows
+                            _ => {
+                                launch_error = Some(errno);
+                                break;
+                            }
+                        }
+                    }
 
                     close(read_fd).ok();
 
+                    // only the child needs the redirected streams and the pty slave
+                    let terminal = stdio.terminal.map(|(master, _)| master);
+
                     let pid = child.as_raw();
                     let process_state = ProcessState::Running;
                     let terminate_on_end = true;
-                    let process = Process::new(Pid::from_raw(pid), terminate_on_end, process_state);
+                    let mut process = Process::new(Pid::from_raw(pid), terminate_on_end, process_state);
+                    process.launch_options = Some(options.clone());
+                    process.terminal = terminal;
+                    process.pending_stop_at = Some(options.get_stop_at()).filter(|s| *s != StopAt::Exec);
+                    process.randomization_error = randomization_error;
 
-                    if bytes_read > 0 {
+                    if let Some(errno) = launch_error {
                         drop(process);
-                        let msg = String::from_utf8_lossy(&buffer[..bytes_read]).to_string();
-                        return Err(format!("Child process failed with error: {}", msg));
+                        return Err(Error::Launch { errno });
                     }
+                    process.elf_pending = true;
 
                     Ok(process)
                 }
                 Ok(ForkResult::Child) => {
                     close(read_fd).ok(); // we only want to write from the child
 
-                    let traceme_res = ptrace::traceme();
-                    if let Err(e) = traceme_res {
-
-                        let _ = write(&write_fd, format!("Tracing child process failed: {}", e).as_bytes());
-                        eprintln!("Tracing child process failed: {}", e);
-                        close(write_fd).ok();
-                        process::exit(1);
-                    }
-
-                    let program_path_c = CString::new(program_path)
-                        .expect("Cstring conversion failed");
-                    let exec_args = vec![program_path_c.clone()];
-                    let exec_res = execvp(&program_path_c, &exec_args);
-
-                    // if the exec in the above line works fine then we never write something to the pipe
+                    // if the exec works fine then we never write something to the pipe
                     // nor do we ever close it
-
-                    if let Err(e) = exec_res {
-                        let _ = write(&write_fd, format!("Tracing child process failed: {}", e).as_bytes());
-                        eprintln!("Exec failed: {}", e);
-                        close(write_fd).ok();
-                        process::exit(1);
-                    }
-                    unreachable!();
+                    let Err(e) = exec_child(&exec_args, &stdio, &write_fd);
+                    report_to_parent(&write_fd, LAUNCH_REPORT_FAILED, e);
+                    close(write_fd).ok();
+                    // _exit, the child must not run the parent's atexit handlers or flush its buffers
+                    libc::_exit(1);
                 }
                 Err(e) => {
-                    Err(format!("Fork failed: {}", e))
+                    Err(Error::os("fork")(e))
                 }
             }
         }
     }
-    pub fn dispatch_command(&mut self, command: String) {
-        let args: Vec<&str> = command.split_whitespace().collect();
-        let command = args[0];
-        if "continue".starts_with(command) {
-            self.resume();
-            if let Err(e) = self.wait_on_signal(){
-                process::exit(1);
-            } // breakpoint// process stops again
-        } else {
-            eprintln!("unknown command: {}", command)
+    /// Resumes every thread. When a thread still has a stop to report nothing runs, the next
+    /// [`Process::wait_on_signal`] returns that stop.
+    pub fn resume(&mut self) -> Result<()> {
+        self.ensure_alive()?;
+        if self.threads.iter().any(|t| t.pending) {
+            self.process_state = ProcessState::Running;
+            return Ok(());
+        }
+        // threads sitting on one of our breakpoints run the original instruction first or they would trap right away
+        let current = self.current_tid;
+        let on_breakpoint: Vec<Pid> = self.threads
+            .iter()
+            .filter(|t| t.tid() == current || t.stop_reason.is_some_and(|r| r.is_breakpoint()))
+            .filter(|t| self.breakpoint_sites.enabled_stoppoint_at_address(t.get_pc()))
+            .map(|t| t.tid())
+            .collect();
+        for tid in on_breakpoint {
+            self.current_tid = tid;
+            let stepped = self.step_instruction();
+            self.current_tid = if self.thread(current).is_some() { current } else { self.pid };
+            stepped?;
+        }
+        for thread in self.threads.iter_mut().filter(|t| t.state == ProcessState::Stopped) {
+            // the signal a thread stopped with goes on to the program, like in GDB
+            ptrace::cont(thread.tid(), thread.pending_signal()).map_err(Error::ptrace("PTRACE_CONT", thread.tid()))?;
+            thread.state = ProcessState::Running;
+            thread.stop_reason = None;
         }
+        self.process_state = ProcessState::Running;
+        Ok(())
     }
-    fn resume(&mut self){
-        if let Err(e) = ptrace::cont(self.pid(), None){
-            eprintln!("Couldn't Continue: {}", e);
-            process::exit(1);
+    /// Executes exactly one instruction in the current thread, the others stay stopped. An
+    /// enabled breakpoint at the current pc is lifted for the duration of the step so the
+    /// original instruction runs instead of the trap.
+    pub fn step_instruction(&mut self) -> Result<StopReason> {
+        let tid = self.current_tid;
+        let pc = self.get_pc();
+        let site_at_pc = self.breakpoint_sites
+            .get_by_address(pc)
+            .filter(|s| s.is_enabled())
+            .map(|s| s.id());
+        if let Some(id) = site_at_pc {
+            self.disable_breakpoint_site(id)?;
         }
+        // with a signal to deliver the step ends at the first instruction of its handler
+        let signal = self.thread(tid).and_then(|t| t.pending_signal());
+        ptrace::step(tid, signal).map_err(Error::ptrace("PTRACE_SINGLESTEP", tid))?;
+        let thread = self.thread_mut(tid).unwrap();
+        thread.state = ProcessState::Running;
+        thread.stop_reason = None;
         self.process_state = ProcessState::Running;
+        self.stepping_tid = Some(tid);
+        let reason = self.collect_stop();
+        self.stepping_tid = None;
+        let reason = reason?;
+        if let ProcessState::Stopped = self.process_state {
+            if let Some(id) = site_at_pc {
+                self.enable_breakpoint_site(id)?;
+            }
+            self.update_inline_height();
+        }
+        Ok(reason)
+    }
+    /// Steps to the next source line, into calls of functions with line information and past
+    /// their prologue. Calls of functions without it are run to their return. At the start of
+    /// inlined code the next inlined function is entered without running anything.
+    pub fn step_in(&mut self) -> Result<StopReason> {
+        if self.inline_height > 0 {
+            self.inline_height -= 1;
+            return Ok(STEP_DONE);
+        }
+        let Some(start) = self.source_location() else {
+            return self.step_out();
+        };
+        let frames = self.frame_entries();
+        loop {
+            let return_address = call_return_address(self, self.get_pc())?;
+            let reason = self.step_instruction()?;
+            if reason.info != StopInfo::Trap(TrapKind::SingleStep) {
+                return Ok(reason);
+            }
+            if self.line_entry_at(self.get_pc()).is_none() {
+                let Some(return_address) = return_address else {
+                    // returned into code without line information, there is no line to go to
+                    return Ok(reason);
+                };
+                // the call pushed the return address, once it is popped we are back in our frame
+                let frame = self.stack_pointer() + 8;
+                if let Some(reason) = self.run_to(return_address, frame)? {
+                    return Ok(reason);
+                }
+            }
+            if let Some(reason) = self.breakpoint_stop() {
+                return Ok(reason);
+            }
+            if self.frame_entries() != frames {
+                break;
+            }
+            if self.is_new_line(&start) {
+                return Ok(reason);
+            }
+        }
+        self.skip_prologue()
+    }
+    /// Steps to the next source line of the current function, running calls and inlined
+    /// functions to their end. Recursive calls are run until the call we made returns rather
+    /// than any call of the function.
+    pub fn step_over(&mut self) -> Result<StopReason> {
+        let Some(start) = self.source_location() else {
+            return self.step_out();
+        };
+        let frames = self.frame_entries();
+        if self.inline_height > 0 {
+            // at the call of an inlined function, its code runs as part of the line
+            let function = self.function_frames_at(self.get_pc())[self.inline_height - 1].clone();
+            while self.function_contains(&function, self.get_pc()) {
+                if let Some(reason) = self.step_over_instruction()? {
+                    return Ok(reason);
+                }
+            }
+            if self.line_entry_at(self.get_pc()).is_none() || self.is_new_line(&start) {
+                return Ok(STEP_DONE);
+            }
+        }
+        loop {
+            if let Some(reason) = self.step_over_instruction()? {
+                return Ok(reason);
+            }
+            if self.line_entry_at(self.get_pc()).is_none() {
+                return Ok(STEP_DONE);
+            }
+            if let Some(reason) = self.breakpoint_stop() {
+                return Ok(reason);
+            }
+            let now = self.frame_entries();
+            // deeper down is code inlined into our line, anything else is where we returned to
+            let inlined_callee = now.len() > frames.len() && now.ends_with(&frames);
+            if inlined_callee {
+                continue;
+            }
+            if now != frames || self.is_new_line(&start) {
+                return Ok(STEP_DONE);
+            }
+        }
+    }
+    /// Runs until the current function returns. An inlined function is stepped through to the
+    /// end of its code instead. The return address is found with the call frame information,
+    /// without it there is no telling and [`Error::NoFrameInfo`] comes back.
+    pub fn step_out(&mut self) -> Result<StopReason> {
+        let frames = self.function_frames();
+        if let Some(function) = frames.first().filter(|f| f.is_inlined()).map(|f| (*f).clone()) {
+            while self.function_contains(&function, self.get_pc()) {
+                if let Some(reason) = self.step_over_instruction()? {
+                    return Ok(reason);
+                }
+            }
+            return Ok(STEP_DONE);
+        }
+        let pc = self.get_pc();
+        let rule = self.elf()
+            .and_then(|elf| return_address_rule(elf, pc.to_file_addr(elf)?.addr()))
+            .ok_or(Error::NoFrameInfo(pc))?;
+        let cfa = rule.cfa(self.registers().read_by_id_as::<u64>(rule.cfa_register));
+        let slot = VirtAddr::new(rule.slot(cfa));
+        let return_address = u64::from_le_bytes(self.read_memory(slot, 8)?.try_into().unwrap());
+        // back in the caller the stack pointer is the canonical frame address
+        match self.run_to(VirtAddr::new(return_address), cfa)? {
+            Some(reason) => Ok(reason),
+            None => Ok(STEP_DONE),
+        }
+    }
+    /// Runs the instruction at the pc and, when it is a call, everything up to the return.
+    /// `None` when that went through, the stop that got in the way otherwise.
+    fn step_over_instruction(&mut self) -> Result<Option<StopReason>> {
+        if let Some(return_address) = call_return_address(self, self.get_pc())? {
+            let frame = self.stack_pointer();
+            return self.run_to(return_address, frame);
+        }
+        let reason = self.step_instruction()?;
+        Ok(Some(reason).filter(|r| r.info != StopInfo::Trap(TrapKind::SingleStep)))
+    }
+    /// Resumes until the current thread gets to `address` with its stack pointer at `frame`
+    /// or above, deeper recursions of the same code run on. Uses an internal breakpoint, or a
+    /// disabled one of the user's enabled for the time being, unless there is an enabled one
+    /// already. `None` once there, the stop that got in the way otherwise.
+    fn run_to(&mut self, address: VirtAddr, frame: u64) -> Result<Option<StopReason>> {
+        let tid = self.current_tid;
+        let (id, owned) = match self.breakpoint_sites.get_by_address(address) {
+            Some(site) if site.is_enabled() => (site.id(), false),
+            Some(site) => (site.id(), true),
+            None => {
+                let id = self.breakpoint_sites.next_internal_id();
+                self.breakpoint_sites.push(BreakpointSite::new(id, self.pid, address, false, true));
+                (id, true)
+            }
+        };
+        let result = self.run_to_site(tid, id, owned, frame);
+        if owned {
+            if let ProcessState::Stopped = self.process_state {
+                self.disable_breakpoint_site(id)?;
+            }
+            if id < 0 {
+                self.breakpoint_sites.remove_by_id(id);
+            }
+        }
+        result
+    }
+    fn run_to_site(&mut self, tid: Pid, id: i32, owned: bool, frame: u64) -> Result<Option<StopReason>> {
+        if owned {
+            self.enable_breakpoint_site(id)?;
+        }
+        loop {
+            self.resume()?;
+            let reason = self.wait_on_signal()?;
+            let at_site = matches!(
+                reason.info,
+                StopInfo::Trap(TrapKind::SoftwareBreakpoint(hit) | TrapKind::HardwareBreakpoint(hit)) if hit == id
+            );
+            if !at_site {
+                return Ok(Some(reason));
+            }
+            if self.current_tid == tid && self.stack_pointer() >= frame {
+                return Ok(None);
+            }
+            // another thread or a deeper recursion got there, a breakpoint of the user's still counts
+            if !owned {
+                return Ok(Some(reason));
+            }
+        }
+    }
+    /// Moves on from the first instruction of a function to where the line after its opening
+    /// line starts, so the arguments are in place.
+    fn skip_prologue(&mut self) -> Result<StopReason> {
+        let pc = self.get_pc();
+        let entry = match self.function_frames().first() {
+            Some(function) if !function.is_inlined() => function.entry,
+            _ => return Ok(STEP_DONE),
+        };
+        let elf = self.elf().unwrap();
+        if FileAddr::new(elf, entry).to_virt_addr() != pc {
+            return Ok(STEP_DONE);
+        }
+        let Some(table) = elf.line_table() else {
+            return Ok(STEP_DONE);
+        };
+        let body = table
+            .entries()
+            .iter()
+            .skip_while(|e| e.address != entry)
+            .find(|e| e.address > entry)
+            .map(|e| FileAddr::new(elf, e.address).to_virt_addr());
+        let Some(body) = body.filter(|body| self.function_frames_at(*body).iter().any(|f| f.entry == entry)) else {
+            return Ok(STEP_DONE);
+        };
+        match self.run_to(body, 0)? {
+            Some(reason) => Ok(reason),
+            None => Ok(STEP_DONE),
+        }
+    }
+    /// The entries of [`Process::function_frames`], which tell the functions apart.
+    fn frame_entries(&self) -> Vec<u64> {
+        self.function_frames().iter().map(|f| f.entry).collect()
+    }
+    fn function_contains(&self, function: &Function, address: VirtAddr) -> bool {
+        self.elf()
+            .and_then(|elf| address.to_file_addr(elf))
+            .is_some_and(|address| function.contains(address.addr()))
+    }
+    /// The pc is at the start of a statement on another line than `start`, or at the call of
+    /// an inlined function on another line.
+    fn is_new_line(&self, start: &(Rc<Path>, u64)) -> bool {
+        let Some(location) = self.source_location() else {
+            return false;
+        };
+        if location == *start {
+            return false;
+        }
+        self.inline_height > 0 || self.line_entry_at(self.get_pc()).is_some_and(|e| {
+            e.is_stmt && self.elf().is_some_and(|elf| FileAddr::new(elf, e.address).to_virt_addr() == self.get_pc())
+        })
     }
-    fn wait_on_signal(&mut self) -> Result<WaitStatus, Errno>{
-        let wait_res = waitpid(self.pid, None);
-        match wait_res {
-            Ok(status) => {
+    /// A stepping stop right at an enabled breakpoint of the user's, reported as a hit of it.
+    fn breakpoint_stop(&self) -> Option<StopReason> {
+        let site = self.breakpoint_sites.get_by_address(self.get_pc()).filter(|s| s.is_enabled() && !s.is_internal())?;
+        let kind = match site.is_hardware() {
+            true => TrapKind::HardwareBreakpoint(site.id()),
+            false => TrapKind::SoftwareBreakpoint(site.id()),
+        };
+        Some(StopReason { state: ProcessState::Stopped, info: StopInfo::Trap(kind) })
+    }
+    fn stack_pointer(&self) -> u64 {
+        self.registers().read_by_id_as::<u64>(RegisterId::Rsp)
+    }
+    /// Works out what a SIGTRAP stop of `tid` was for and fixes up the thread where needed.
+    /// Returns `None` when the SIGTRAP was sent like any other signal (kill, the exec trap).
+    fn handle_sigtrap(&mut self, tid: Pid) -> Result<Option<TrapKind>> {
+        let info = ptrace::getsiginfo(tid).map_err(Error::ptrace("PTRACE_GETSIGINFO", tid))?;
+        match info.si_code {
+            libc::TRAP_HWBKPT => match self.hardware_stoppoint_of(self.thread(tid).unwrap()) {
+                Some(HardwareStoppoint::Watchpoint(id)) => {
+                    let watchpoint = self.watchpoints.get_by_id(id).unwrap();
+                    let data = self.read_watched_value(watchpoint.address(), watchpoint.size())?;
+                    let watchpoint = self.watchpoints.get_by_id_mut(id).unwrap();
+                    watchpoint.update_data(data);
+                    Ok(Some(TrapKind::Watchpoint { id, old_value: watchpoint.previous_data(), new_value: data }))
+                }
+                // hardware execution breakpoints fault before the instruction runs, the pc is already right
+                Some(HardwareStoppoint::BreakpointSite(id)) => Ok(Some(TrapKind::HardwareBreakpoint(id))),
+                None => Ok(Some(TrapKind::SingleStep))
+            },
+            // a step that enters a signal handler ends with TRAP_UNK, or TRAP_BRKPT on older
+            // kernels, rather than TRAP_TRACE
+            libc::TRAP_UNK | libc::TRAP_BRKPT if self.stepping_tid == Some(tid) => Ok(Some(TrapKind::SingleStep)),
+            // int3 is reported as SI_KERNEL, a single step as TRAP_TRACE
+            libc::SI_KERNEL | libc::TRAP_BRKPT => {
+                // after an int3 the pc is one past the breakpoint, rewind it onto the site
+                let address = self.thread(tid).unwrap().get_pc().wrapping_sub(1);
+                let site = self.breakpoint_sites
+                    .get_by_address(address)
+                    .filter(|s| s.is_enabled() && !s.is_hardware())
+                    .map(|s| s.id());
+                match site {
+                    Some(id) => {
+                        self.thread_mut(tid).unwrap().registers.write_by_id(RegisterId::Rip, address.addr())?;
+                        Ok(Some(TrapKind::SoftwareBreakpoint(id)))
+                    }
+                    None => Ok(Some(TrapKind::Unknown))
+                }
+            }
+            libc::TRAP_TRACE => Ok(Some(TrapKind::SingleStep)),
+            // SI_USER, SI_TKILL and the other user generated codes are all <= 0
+            code if code <= 0 => Ok(None),
+            _ => Ok(Some(TrapKind::Unknown))
+        }
+    }
+    /// Waits until a thread stops or the process ends. Once one thread stops all of them are
+    /// stopped, the one that stopped becomes the current thread.
+    pub fn wait_on_signal(&mut self) -> Result<StopReason> {
+        let reason = self.wait_for_stop()?;
+        let reason = match self.pending_stop_at.take() {
+            Some(stop_at) if reason.info == StopInfo::Signal(Signal::SIGTRAP) => self.run_to_launch_stop(stop_at)?,
+            _ => reason,
+        };
+        if let ProcessState::Stopped = self.process_state {
+            self.update_inline_height();
+        }
+        Ok(reason)
+    }
+    /// Runs a process sitting at its exec trap up to the entry point or `main` with an internal
+    /// breakpoint. The stop is reported as a plain SIGTRAP, like the exec trap it stands in for.
+    fn run_to_launch_stop(&mut self, stop_at: StopAt) -> Result<StopReason> {
+        let address = self.launch_stop_address(stop_at)?;
+        let id = self.breakpoint_sites.next_internal_id();
+        self.breakpoint_sites.push(BreakpointSite::new(id, self.pid, address, false, true));
+        self.enable_breakpoint_site(id)?;
+        self.resume()?;
+        let mut reason = self.wait_for_stop()?;
+        if let ProcessState::Stopped = self.process_state {
+            self.remove_breakpoint_site(id)?;
+        } else {
+            // nothing left to restore the byte in
+            self.breakpoint_sites.remove_by_id(id);
+        }
+        if reason.info == StopInfo::Trap(TrapKind::SoftwareBreakpoint(id)) {
+            reason.info = StopInfo::Signal(Signal::SIGTRAP);
+        }
+        Ok(reason)
+    }
+    /// The runtime address of the entry point comes from AT_ENTRY, `main` from the symbol table.
+    fn launch_stop_address(&self, stop_at: StopAt) -> Result<VirtAddr> {
+        let entry = VirtAddr::new(auxv_entry(self.pid, AT_ENTRY)?);
+        if stop_at != StopAt::Main {
+            return Ok(entry);
+        }
+        // stripped executables have no main to find, stop at the entry point instead
+        Ok(self.lookup_symbol("main").unwrap_or(entry))
+    }
+    /// Reports a stop collected earlier while stopping the threads, or else waits for a new one.
+    /// Forks and execs are dealt with before they are reported.
+    fn wait_for_stop(&mut self) -> Result<StopReason> {
+        let reason = match self.threads.iter_mut().find(|t| t.pending) {
+            Some(thread) => {
+                thread.pending = false;
+                self.current_tid = thread.tid();
                 self.process_state = ProcessState::Stopped;
-                Ok(status)
+                thread.stop_reason.unwrap()
+            }
+            None => self.collect_stop()?,
+        };
+        if self.elf_pending && let ProcessState::Stopped = self.process_state {
+            self.elf_pending = false;
+            self.load_elf();
+        }
+        match reason.info {
+            StopInfo::Trap(TrapKind::Fork) => self.follow_fork(false)?,
+            StopInfo::Trap(TrapKind::Vfork) => self.follow_fork(true)?,
+            StopInfo::Trap(TrapKind::Exec) => self.handle_exec()?,
+            _ => {}
+        }
+        Ok(reason)
+    }
+    /// Takes over the child of the fork the current thread just reported. Afterwards `self` is
+    /// the side [`Process::follow_fork_mode`] asks for, the other one is detached from or kept
+    /// for [`Process::take_unfollowed`].
+    fn follow_fork(&mut self, vfork: bool) -> Result<()> {
+        let tid = self.current_tid;
+        let child_pid = ptrace::getevent(tid).map_err(Error::ptrace("PTRACE_GETEVENTMSG", tid))?;
+        let child_pid = Pid::from_raw(child_pid as i32);
+        // the child is traced from the start and begins with a stop, SIGSTOP or PTRACE_EVENT_STOP
+        loop {
+            match waitpid(child_pid, Some(WaitPidFlag::__WALL)) {
+                Ok(WaitStatus::Stopped(..) | WaitStatus::PtraceEvent(..)) => break,
+                // killed before it ever ran, there is nothing to follow
+                Ok(WaitStatus::Exited(..) | WaitStatus::Signaled(..)) | Err(Errno::ECHILD) => return Ok(()),
+                Ok(_) => {}
+                Err(errno) => return Err(Error::os("waitpid")(errno)),
+            }
+        }
+        let mut child = Process::new(child_pid, self.terminate_on_end, ProcessState::Stopped);
+        child.is_seized = self.is_seized;
+        child.options_set = true;
+        child.follow_fork_mode = self.follow_fork_mode;
+        child.detach_on_fork = self.detach_on_fork;
+        child.launch_options = self.launch_options.clone();
+        child.elf = self.elf.clone();
+        let thread = child.thread_mut(child_pid).unwrap();
+        thread.state = ProcessState::Stopped;
+        thread.registers.read_all()?;
+        child.inherit_stoppoints(self, vfork)?;
+        if vfork {
+            // until the child execs or exits it runs on our memory, int3s included
+            let lifted: Vec<i32> = self.breakpoint_sites
+                .iter()
+                .filter(|s| s.is_enabled() && !s.is_hardware())
+                .map(|s| s.id())
+                .collect();
+            for id in &lifted {
+                self.disable_breakpoint_site(*id)?;
+            }
+            self.vfork_lifted = lifted;
+        }
+        if self.follow_fork_mode == FollowForkMode::Child {
+            std::mem::swap(self, &mut child);
+            // the program's terminal stays with whichever side we debug
+            std::mem::swap(&mut self.terminal, &mut child.terminal);
+        }
+        if self.detach_on_fork {
+            child.detach()?;
+        } else {
+            self.unfollowed = Some(Box::new(child));
+        }
+        Ok(())
+    }
+    /// Takes over the breakpoints and watchpoints of the process we forked from. Software sites
+    /// are in the copied memory already, the debug registers are not inherited and are set anew.
+    fn inherit_stoppoints(&mut self, parent: &Process, shares_memory: bool) -> Result<()> {
+        self.breakpoint_sites = parent.breakpoint_sites.clone();
+        self.watchpoints = parent.watchpoints.clone();
+        let mut hardware = Vec::new();
+        for site in self.breakpoint_sites.iter_mut() {
+            site.set_pid(self.pid);
+            if !site.is_enabled() {
+                continue;
+            }
+            if site.is_hardware() {
+                site.mark_removed();
+                hardware.push(site.id());
+            } else if shares_memory {
+                // the parent takes them out of the shared memory
+                site.mark_removed();
+                self.vfork_lifted.push(site.id());
+            }
+        }
+        for id in hardware {
+            self.enable_breakpoint_site(id)?;
+        }
+        let watchpoints: Vec<i32> = self.watchpoints
+            .iter_mut()
+            .filter(|w| w.is_enabled())
+            .map(|w| {
+                w.set_hardware_slot(None);
+                w.id()
+            })
+            .collect();
+        for id in watchpoints {
+            self.enable_watchpoint(id)?;
+        }
+        Ok(())
+    }
+    /// An exec replaced the memory image and cleared the debug registers. The new executable is
+    /// loaded, and when it is the same program loaded at the same place enabled stoppoints are
+    /// set again, where it maps nothing they stay disabled. After an exec of another program
+    /// they all stay disabled.
+    fn handle_exec(&mut self) -> Result<()> {
+        let old_elf = self.elf.take();
+        self.load_elf();
+        // the exec took our int3s and debug registers along with the old memory
+        let mut sites: Vec<i32> = self.breakpoint_sites
+            .iter_mut()
+            .filter(|s| s.is_enabled())
+            .map(|s| {
+                s.mark_removed();
+                s.id()
+            })
+            .collect();
+        sites.append(&mut self.vfork_lifted);
+        let watchpoints: Vec<i32> = self.watchpoints
+            .iter_mut()
+            .filter(|w| w.is_enabled())
+            .map(|w| {
+                w.set_hardware_slot(None);
+                w.id()
+            })
+            .collect();
+        let same_image = match (&old_elf, &self.elf) {
+            (Some(old), Some(new)) => is_same_image(old, new),
+            _ => false,
+        };
+        if !same_image {
+            // the addresses were those of another program, patching them would corrupt this one
+            return Ok(());
+        }
+        for id in sites {
+            let _ = self.enable_breakpoint_site(id);
+        }
+        for id in watchpoints {
+            let watchpoint = self.watchpoints.get_by_id(id).unwrap();
+            if let Ok(data) = self.read_watched_value(watchpoint.address(), watchpoint.size()) {
+                self.watchpoints.get_by_id_mut(id).unwrap().update_data(data);
+            }
+            self.enable_watchpoint(id)?;
+        }
+        Ok(())
+    }
+    /// Waits for the next stop worth reporting, then stops the other threads as well.
+    fn collect_stop(&mut self) -> Result<StopReason> {
+        let reason = loop {
+            let (tid, status) = self.wait_for_any_thread()?;
+            match self.handle_thread_status(tid, status)? {
+                ThreadEvent::Report(reason) => {
+                    if self.thread(tid).is_some() {
+                        self.current_tid = tid;
+                    }
+                    break reason;
+                }
+                // a new thread checking in, it runs along with the others
+                ThreadEvent::Requested => self.continue_thread(tid, None)?,
+                ThreadEvent::Handled => {}
+            }
+        };
+        self.process_state = reason.state;
+        if let ProcessState::Stopped = self.process_state
+            && let Some(end) = self.stop_all_threads()? {
+            return Ok(end);
+        }
+        Ok(reason)
+    }
+    /// Stops every running thread and waits until all of them are. Stops that happen on the way
+    /// and are not the ones we asked for are kept to be reported by the next wait. Returns how
+    /// the process ended if it did so in the meantime.
+    fn stop_all_threads(&mut self) -> Result<Option<StopReason>> {
+        let (pid, is_seized) = (self.pid, self.is_seized);
+        for thread in self.threads.iter_mut().filter(|t| t.state == ProcessState::Running && !t.stop_requested) {
+            let tid = thread.tid();
+            // SIGSTOP to a single thread, kill would stop whichever thread the kernel picks
+            let result = if is_seized {
+                ptrace::interrupt(tid)
+            } else {
+                Errno::result(unsafe { libc::syscall(libc::SYS_tgkill, pid.as_raw(), tid.as_raw(), libc::SIGSTOP) }).map(drop)
+            };
+            match result {
+                // ending already, its exit is collected below
+                Ok(()) | Err(Errno::ESRCH) => thread.stop_requested = true,
+                Err(errno) => return Err(Error::ptrace(if is_seized { "PTRACE_INTERRUPT" } else { "tgkill" }, tid)(errno)),
+            }
+        }
+        while self.threads.iter().any(|t| t.state == ProcessState::Running) {
+            let (tid, status) = self.wait_for_any_thread()?;
+            match self.handle_thread_status(tid, status)? {
+                ThreadEvent::Report(reason) if reason.state == ProcessState::Stopped => {
+                    self.thread_mut(tid).unwrap().pending = true;
+                }
+                ThreadEvent::Report(reason) => {
+                    self.process_state = reason.state;
+                    return Ok(Some(reason));
+                }
+                ThreadEvent::Requested | ThreadEvent::Handled => {}
+            }
+        }
+        Ok(None)
+    }
+    /// Lets a single thread go again after a stop nobody needs to hear about, delivering
+    /// `signal` if there is one. The thread being stepped is stepped, not continued.
+    fn continue_thread(&mut self, tid: Pid, signal: Option<Signal>) -> Result<()> {
+        let result = if self.stepping_tid == Some(tid) {
+            ptrace::step(tid, signal)
+        } else {
+            ptrace::cont(tid, signal)
+        };
+        match result {
+            // killed while stopped, the exit is still to be collected
+            Ok(()) | Err(Errno::ESRCH) => {}
+            Err(errno) => return Err(Error::ptrace("PTRACE_CONT", tid)(errno)),
+        }
+        self.thread_mut(tid).unwrap().state = ProcessState::Running;
+        Ok(())
+    }
+    /// The next wait status of any running thread. Threads are waited for by tid, a new thread
+    /// is only waited for once the clone event of its parent added it.
+    fn wait_for_any_thread(&mut self) -> Result<(Pid, WaitStatus)> {
+        loop {
+            let mut tids: Vec<Pid> = self.threads
+                .iter()
+                .filter(|t| t.state == ProcessState::Running)
+                .map(|t| t.tid())
+                .collect();
+            if tids.is_empty() {
+                tids.push(self.pid);
+            }
+            // a single thread can be waited for blocking, several have to be polled in turn
+            let polling = tids.len() > 1;
+            let flags = if polling { WaitPidFlag::__WALL | WaitPidFlag::WNOHANG } else { WaitPidFlag::__WALL };
+            for tid in tids {
+                match waitpid(tid, Some(flags)) {
+                    Ok(WaitStatus::StillAlive) => {}
+                    Ok(status) => return Ok((tid, status)),
+                    // reaped as part of the process going away
+                    Err(Errno::ECHILD) if tid != self.pid => self.remove_thread(tid),
+                    Err(Errno::ECHILD) => {
+                        self.process_state = ProcessState::Terminated;
+                        return Err(Error::NoSuchProcess(self.pid.as_raw()));
+                    }
+                    Err(errno) => return Err(Error::os("waitpid")(errno)),
+                }
+            }
+            if polling {
+                std::thread::sleep(THREAD_POLL_INTERVAL);
+            }
+        }
+    }
+    fn remove_thread(&mut self, tid: Pid) {
+        self.threads.retain(|t| t.tid() != tid);
+        if self.current_tid == tid {
+            self.current_tid = self.pid;
+        }
+    }
+    /// Updates the thread `tid` for the wait status it reported and decides what the status
+    /// means for the process.
+    fn handle_thread_status(&mut self, tid: Pid, status: WaitStatus) -> Result<ThreadEvent> {
+        let mut reason = StopReason::from_wait_status(status);
+        if reason.state != ProcessState::Stopped {
+            if tid != self.pid {
+                self.remove_thread(tid);
+                return Ok(ThreadEvent::Handled);
+            }
+            // the leader is only reported once every other thread is gone
+            self.threads.retain(|t| t.tid() == tid);
+            let leader = self.thread_mut(tid).unwrap();
+            leader.state = reason.state;
+            leader.stop_reason = Some(reason);
+            self.current_tid = tid;
+            return Ok(ThreadEvent::Report(reason));
+        }
+        if !self.options_set {
+            // a launched process stops at its exec trap with a single thread, new ones inherit the options
+            ptrace::setoptions(tid, TRACE_OPTIONS)
+                .map_err(Error::ptrace("PTRACE_SETOPTIONS", tid))?;
+            self.options_set = true;
+        }
+        let thread = self.add_thread(tid);
+        thread.state = ProcessState::Stopped;
+        thread.registers.read_all()?;
+        if thread.needs_debug_registers {
+            self.copy_debug_registers(tid)?;
+        }
+        if let WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_VFORK_DONE) = status {
+            // the vfork child has let go of our memory, through `tid` as the leader may be running
+            for id in std::mem::take(&mut self.vfork_lifted) {
+                let Some(site) = self.breakpoint_sites.get_by_id_mut(id) else {
+                    continue;
+                };
+                site.set_pid(tid);
+                let enabled = site.enable();
+                site.set_pid(self.pid);
+                enabled?;
+            }
+            self.continue_thread(tid, None)?;
+            return Ok(ThreadEvent::Handled);
+        }
+        let thread = self.thread_mut(tid).unwrap();
+        match reason.info {
+            StopInfo::Trap(TrapKind::Clone) => {
+                let new_tid = ptrace::getevent(tid).map_err(Error::ptrace("PTRACE_GETEVENTMSG", tid))?;
+                // the new thread starts with a stop of its own, and without our debug registers
+                let new_thread = self.add_thread(Pid::from_raw(new_tid as i32));
+                new_thread.stop_requested = true;
+                new_thread.needs_debug_registers = true;
+                self.continue_thread(tid, None)?;
+                Ok(ThreadEvent::Handled)
+            }
+            StopInfo::Signal(Signal::SIGSTOP) | StopInfo::Trap(TrapKind::Interrupt) if thread.stop_requested => {
+                thread.stop_requested = false;
+                thread.stop_reason = None;
+                Ok(ThreadEvent::Requested)
             }
-            Err(e) => {
-                eprintln!("waitpid failed: {}", e);
-                self.process_state=ProcessState::Terminated;
-                Err(e)
+            // a child of the program changed state, e.g. one we detached from after a fork
+            // exited. Not worth a stop, like in GDB it goes straight through to the program.
+            StopInfo::Signal(Signal::SIGCHLD) => {
+                self.continue_thread(tid, Some(Signal::SIGCHLD))?;
+                Ok(ThreadEvent::Handled)
+            }
+            _ => {
+                if let StopInfo::Signal(Signal::SIGTRAP) = reason.info
+                    && let Some(kind) = self.handle_sigtrap(tid)? {
+                    reason.info = StopInfo::Trap(kind);
+                }
+                self.thread_mut(tid).unwrap().stop_reason = Some(reason);
+                Ok(ThreadEvent::Report(reason))
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Whether an exec loaded the program it replaced again, at the same place, so the addresses
+/// of stoppoints still point at the same code. Without a build id the contents have to match.
+fn is_same_image(old: &Elf, new: &Elf) -> bool {
+    if old.load_bias() != new.load_bias() {
+        return false;
+    }
+    match (old.build_id(), new.build_id()) {
+        (Some(old_id), Some(new_id)) => old_id == new_id,
+        _ => old.data() == new.data(),
+    }
+}
+
+/// Sends the parent one report of `kind` about `errno` through the launch pipe.
+fn report_to_parent(pipe: &OwnedFd, kind: u8, errno: Errno) {
+    let mut report = [kind; LAUNCH_REPORT_SIZE];
+    report[1..].copy_from_slice(&(errno as i32).to_ne_bytes());
+    let _ = write(pipe, &report);
+}
+
+/// Runs in the forked child and only returns when something went wrong. Problems that do not
+/// stop the launch are reported through `pipe` on the way.
+fn exec_child(exec_args: &ExecArgs, stdio: &OpenStdio, pipe: &OwnedFd) -> std::result::Result<Infallible, Errno> {
+    if let Some((_, slave)) = &stdio.terminal {
+        // a session of its own with the pty as controlling terminal, so the program gets a real
+        // tty and a ^C typed at the debugger is not delivered to it
+        setsid()?;
+        Errno::result(unsafe { libc::ioctl(slave.as_raw_fd(), libc::TIOCSCTTY, 0) })?;
+    }
+    for (target, fd) in stdio.streams.iter().enumerate() {
+        if let Some(fd) = fd {
+            Errno::result(unsafe { libc::dup2(fd.as_raw_fd(), target as i32) })?;
+        }
+    }
+    if exec_args.disable_randomization {
+        // keep whatever else the persona has, failing is not fatal just like in GDB
+        let disabled = personality::get().and_then(|persona| personality::set(persona | Persona::ADDR_NO_RANDOMIZE));
+        if let Err(e) = disabled {
+            report_to_parent(pipe, LAUNCH_REPORT_PERSONALITY, e);
+        }
+    }
+    for (resource, soft, hard) in &exec_args.rlimits {
+        setrlimit(*resource, *soft, *hard)?;
+    }
+    ptrace::traceme()?;
+    if let Some(dir) = &exec_args.current_dir {
+        chdir(dir.as_c_str())?;
+    }
+    execvpe(&exec_args.program, &exec_args.argv, &exec_args.envp)
+}
+
+/// Whether the process is stopped by a signal (`T` in `/proc/pid/stat`), rather than running or
+/// sleeping.
+fn is_group_stopped(pid: Pid) -> bool {
+    let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid.as_raw())) else {
+        return false;
+    };
+    // the command name in parentheses can contain spaces, the state follows the last ')'
+    stat.rsplit_once(')').and_then(|(_, rest)| rest.split_whitespace().next()) == Some("T")
+}
+
+fn is_traced_by_us(pid: Pid) -> bool {
+    let Ok(status) = std::fs::read_to_string(format!("/proc/{}/status", pid.as_raw())) else {
+        return false;
+    };
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))
+        .and_then(|tracer| tracer.trim().parse::<i32>().ok())
+        // TracerPid is the tid of the tracing thread, not our pid
+        == Some(gettid().as_raw())
+}
+
+/// The tids in `/proc/pid/task`, empty when the process is gone.
+fn thread_ids(pid: Pid) -> Vec<Pid> {
+    let Ok(entries) = std::fs::read_dir(format!("/proc/{}/task", pid.as_raw())) else {
+        return Vec::new();
+    };
+    let mut tids: Vec<Pid> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+        .map(Pid::from_raw)
+        .collect();
+    tids.sort();
+    tids
+}
+
+/// The value of `key` in the auxiliary vector the kernel handed the program at exec.
+fn auxv_entry(pid: Pid, key: u64) -> Result<u64> {
+    let auxv = std::fs::read(format!("/proc/{}/auxv", pid.as_raw()))
+        .map_err(|e| Error::os("read auxv")(Errno::from_raw(e.raw_os_error().unwrap_or(0))))?;
+    auxv.chunks_exact(16)
+        .map(|pair| (u64::from_ne_bytes(pair[..8].try_into().unwrap()), u64::from_ne_bytes(pair[8..].try_into().unwrap())))
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| value)
+        .ok_or_else(|| Error::os("read auxv")(Errno::ENOENT))
+}
@@ -29,15 +29,15 @@ pub enum RegisterId { // 125 registers in total
 }
 
 #[repr(C)]
-struct User {
+pub(crate) struct User {
     /// General purpose registers (GPRs)
-    regs: UserRegsStruct,
+    pub(crate) regs: UserRegsStruct,
     /// Validity flag for FPU state (1 if FPU state is valid)
     u_fpvalid: i32,
     /// Padding for 8-byte alignment
     _pad0: [u8; 4],
     /// Floating point unit state (FPU, MMX, SSE registers)
-    i387: UserFpRegsStruct,
+    pub(crate) i387: UserFpRegsStruct,
     /// Text segment size in pages
     u_tsize: u64,
     /// Data segment size in pages
@@ -61,7 +61,7 @@ struct User {
     /// Command name (process name, null-terminated)
     u_comm: [u8; 32],
     /// Hardware debug registers (dr0-dr7)
-    u_debugreg: [u64; 8],
+    pub(crate) u_debugreg: [u64; 8],
     /// CPU exception error code
     error_code: u64,
     /// Fault address that caused exception
@@ -69,7 +69,7 @@ struct User {
 }
 
 #[repr(C)]
-struct UserRegsStruct {
+pub(crate) struct UserRegsStruct {
     r15: u64,
     r14: u64,
     r13: u64,
@@ -106,7 +106,7 @@ struct UserRegsStruct {
 }
 
 #[repr(C)]
-struct UserFpRegsStruct {
+pub(crate) struct UserFpRegsStruct {
     cwd: u16,           // Control word
     swd: u16,           // Status word
     ftw: u16,           // Tag word
@@ -128,6 +128,11 @@ struct UserFpRegsStruct {
     padding: [u32; 24],
 }
 
+// the kernel hands these blocks over as libc::user_regs_struct / user_fpregs_struct,
+// we copy them byte for byte so the layouts have to line up
+const _: () = assert!(size_of::<UserRegsStruct>() == size_of::<nix::libc::user_regs_struct>());
+const _: () = assert!(size_of::<UserFpRegsStruct>() == size_of::<nix::libc::user_fpregs_struct>());
+
 const fn gpr_offset(reg_offset: usize) -> usize{
     offset_of!(User, regs) + reg_offset
 }
@@ -136,6 +141,14 @@ const fn fpr_offset(reg_offset: usize) -> usize {
     offset_of!(User, i387) + reg_offset
 }
 
+const fn st_offset(number: usize) -> usize {
+    fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + number * 16
+}
+
+const fn xmm_offset(number: usize) -> usize {
+    fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + number * 16
+}
+
 const fn dr_offset(number: usize) -> usize {
     offset_of!(User, u_debugreg) + number * 8
 }
@@ -157,13 +170,13 @@ pub enum RegisterFormat {
 }
 
 pub struct Register {
-    id: RegisterId,
-    name: &'static str,
-    size: usize,
-    offset: usize,
-    register_type: RegisterType,
-    register_format: RegisterFormat,
-    dwarf_id: i32
+    pub id: RegisterId,
+    pub name: &'static str,
+    pub size: usize,
+    pub offset: usize,
+    pub register_type: RegisterType,
+    pub register_format: RegisterFormat,
+    pub dwarf_id: i32
 }
 
 impl Register {
@@ -963,7 +976,7 @@ pub const REGISTERS: &[Register] = &[
         name: "st0",
         dwarf_id: 33,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 0 * 16,
+        offset: st_offset(0),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::LongDouble,
     },
@@ -972,7 +985,7 @@ pub const REGISTERS: &[Register] = &[
         name: "st1",
         dwarf_id: 34,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 1 * 16,
+        offset: st_offset(1),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::LongDouble,
     },
@@ -981,7 +994,7 @@ pub const REGISTERS: &[Register] = &[
         name: "st2",
         dwarf_id: 35,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 2 * 16,
+        offset: st_offset(2),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::LongDouble,
     },
@@ -990,7 +1003,7 @@ pub const REGISTERS: &[Register] = &[
         name: "st3",
         dwarf_id: 36,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 3 * 16,
+        offset: st_offset(3),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::LongDouble,
     },
@@ -999,7 +1012,7 @@ pub const REGISTERS: &[Register] = &[
         name: "st4",
         dwarf_id: 37,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 4 * 16,
+        offset: st_offset(4),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::LongDouble,
     },
@@ -1008,7 +1021,7 @@ pub const REGISTERS: &[Register] = &[
         name: "st5",
         dwarf_id: 38,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 5 * 16,
+        offset: st_offset(5),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::LongDouble,
     },
@@ -1017,7 +1030,7 @@ pub const REGISTERS: &[Register] = &[
         name: "st6",
         dwarf_id: 39,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 6 * 16,
+        offset: st_offset(6),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::LongDouble,
     },
@@ -1026,7 +1039,7 @@ pub const REGISTERS: &[Register] = &[
         name: "st7",
         dwarf_id: 40,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 7 * 16,
+        offset: st_offset(7),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::LongDouble,
     },
@@ -1038,7 +1051,7 @@ pub const REGISTERS: &[Register] = &[
         name: "mm0",
         dwarf_id: 41,
         size: 8,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 0 * 16,
+        offset: st_offset(0),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1047,7 +1060,7 @@ pub const REGISTERS: &[Register] = &[
         name: "mm1",
         dwarf_id: 42,
         size: 8,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 1 * 16,
+        offset: st_offset(1),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1056,7 +1069,7 @@ pub const REGISTERS: &[Register] = &[
         name: "mm2",
         dwarf_id: 43,
         size: 8,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 2 * 16,
+        offset: st_offset(2),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1065,7 +1078,7 @@ pub const REGISTERS: &[Register] = &[
         name: "mm3",
         dwarf_id: 44,
         size: 8,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 3 * 16,
+        offset: st_offset(3),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1074,7 +1087,7 @@ pub const REGISTERS: &[Register] = &[
         name: "mm4",
         dwarf_id: 45,
         size: 8,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 4 * 16,
+        offset: st_offset(4),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1083,7 +1096,7 @@ pub const REGISTERS: &[Register] = &[
         name: "mm5",
         dwarf_id: 46,
         size: 8,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 5 * 16,
+        offset: st_offset(5),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1092,7 +1105,7 @@ pub const REGISTERS: &[Register] = &[
         name: "mm6",
         dwarf_id: 47,
         size: 8,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 6 * 16,
+        offset: st_offset(6),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1101,7 +1114,7 @@ pub const REGISTERS: &[Register] = &[
         name: "mm7",
         dwarf_id: 48,
         size: 8,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, st_space)) + 7 * 16,
+        offset: st_offset(7),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1112,7 +1125,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm0",
         dwarf_id: 17,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 0 * 16,
+        offset: xmm_offset(0),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1121,7 +1134,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm1",
         dwarf_id: 18,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 1 * 16,
+        offset: xmm_offset(1),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1130,7 +1143,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm2",
         dwarf_id: 19,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 2 * 16,
+        offset: xmm_offset(2),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1139,7 +1152,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm3",
         dwarf_id: 20,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 3 * 16,
+        offset: xmm_offset(3),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1148,7 +1161,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm4",
         dwarf_id: 21,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 4 * 16,
+        offset: xmm_offset(4),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1157,7 +1170,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm5",
         dwarf_id: 22,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 5 * 16,
+        offset: xmm_offset(5),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1166,7 +1179,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm6",
         dwarf_id: 23,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 6 * 16,
+        offset: xmm_offset(6),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1175,7 +1188,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm7",
         dwarf_id: 24,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 7 * 16,
+        offset: xmm_offset(7),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1184,7 +1197,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm8",
         dwarf_id: 25,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 8 * 16,
+        offset: xmm_offset(8),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1193,7 +1206,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm9",
         dwarf_id: 26,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 9 * 16,
+        offset: xmm_offset(9),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1202,7 +1215,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm10",
         dwarf_id: 27,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 10 * 16,
+        offset: xmm_offset(10),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1211,7 +1224,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm11",
         dwarf_id: 28,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 11 * 16,
+        offset: xmm_offset(11),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1220,7 +1233,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm12",
         dwarf_id: 29,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 12 * 16,
+        offset: xmm_offset(12),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1229,7 +1242,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm13",
         dwarf_id: 30,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 13 * 16,
+        offset: xmm_offset(13),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1238,7 +1251,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm14",
         dwarf_id: 31,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 14 * 16,
+        offset: xmm_offset(14),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -1247,7 +1260,7 @@ pub const REGISTERS: &[Register] = &[
         name: "xmm15",
         dwarf_id: 32,
         size: 16,
-        offset: fpr_offset(offset_of!(UserFpRegsStruct, xmm_space)) + 15 * 16,
+        offset: xmm_offset(15),
         register_type: RegisterType::Fpr,
         register_format: RegisterFormat::Vector,
     },
@@ -0,0 +1,237 @@
+use std::fmt;
+use crate::error::{Error, Result};
+use crate::rdb::register_info::{Register, RegisterFormat};
+use crate::utils::parse::{parse_f64, parse_u64, parse_vector};
+
+/// x87 80-bit extended precision float, stored as its 10 little endian bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct F80(pub [u8; 10]);
+
+/// A value read from or about to be written into a register.
+///
+/// Writes are checked against the target register, nothing is ever silently truncated:
+/// - `Uint` registers take integers no wider than the register. Unsigned values are zero
+///   extended and signed values sign extended to the register width, so `I8(-1)` written
+///   to `rax` gives `0xffffffffffffffff`. Sub-registers (`eax`, `ax`, `ah`, `r8b`, ...) only
+///   ever change their own bytes of the full register.
+/// - `DoubleFloat` registers take `F32` or `F64`, `LongDouble` registers (`st0`-`st7`)
+///   take `F32`, `F64` or `F80`. Floats are widened exactly, never narrowed.
+/// - `Vector` registers take byte arrays no longer than the register, `Byte64` written to
+///   an `xmm` register clears the upper 8 bytes.
+///
+/// Reads always produce the register's natural type, see [`RegisterValue::from_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegisterValue {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    F80(F80),
+    Byte64([u8; 8]),
+    Byte128([u8; 16]),
+}
+
+impl RegisterValue {
+    /// Interprets the raw bytes of `info` according to its size and format.
+    pub fn from_bytes(info: &Register, bytes: &[u8]) -> Self {
+        match (info.register_format, info.size) {
+            (RegisterFormat::Uint, 1) => RegisterValue::U8(bytes[0]),
+            (RegisterFormat::Uint, 2) => RegisterValue::U16(u16::from_le_bytes(bytes[..2].try_into().unwrap())),
+            (RegisterFormat::Uint, 4) => RegisterValue::U32(u32::from_le_bytes(bytes[..4].try_into().unwrap())),
+            (RegisterFormat::Uint, _) => RegisterValue::U64(u64::from_le_bytes(bytes[..8].try_into().unwrap())),
+            (RegisterFormat::DoubleFloat, _) => RegisterValue::F64(f64::from_le_bytes(bytes[..8].try_into().unwrap())),
+            (RegisterFormat::LongDouble, _) => RegisterValue::F80(F80(bytes[..10].try_into().unwrap())),
+            (RegisterFormat::Vector, 8) => RegisterValue::Byte64(bytes[..8].try_into().unwrap()),
+            (RegisterFormat::Vector, _) => RegisterValue::Byte128(bytes[..16].try_into().unwrap()),
+        }
+    }
+
+    /// Parses user input into the natural type of `info`, this is what `register write` uses.
+    pub fn parse(info: &Register, text: &str) -> Result<Self> {
+        let value = match info.register_format {
+            RegisterFormat::Uint if text.starts_with('-') => {
+                let value = text.parse::<i64>().map_err(|_| Error::Parse(format!("Invalid integer: {}", text)))?;
+                match info.size {
+                    1 => i8::try_from(value).map(RegisterValue::I8).ok(),
+                    2 => i16::try_from(value).map(RegisterValue::I16).ok(),
+                    4 => i32::try_from(value).map(RegisterValue::I32).ok(),
+                    _ => Some(RegisterValue::I64(value)),
+                }
+            }
+            RegisterFormat::Uint => {
+                let value = parse_u64(text)?;
+                match info.size {
+                    1 => u8::try_from(value).map(RegisterValue::U8).ok(),
+                    2 => u16::try_from(value).map(RegisterValue::U16).ok(),
+                    4 => u32::try_from(value).map(RegisterValue::U32).ok(),
+                    _ => Some(RegisterValue::U64(value)),
+                }
+            }
+            RegisterFormat::DoubleFloat => Some(RegisterValue::F64(parse_f64(text)?)),
+            RegisterFormat::LongDouble => Some(RegisterValue::F80(F80::from_f64(parse_f64(text)?))),
+            RegisterFormat::Vector => {
+                let bytes = parse_vector(text)?;
+                if bytes.len() != info.size {
+                    return Err(Error::InvalidRegister(format!(
+                        "{} takes exactly {} bytes, got {}", info.name, info.size, bytes.len()
+                    )));
+                }
+                Some(RegisterValue::from_bytes(info, &bytes))
+            }
+        };
+        value.ok_or_else(|| Error::InvalidRegister(format!("{} does not fit in {} ({} bytes)", text, info.name, info.size)))
+    }
+
+    /// Width of the value itself, before any widening.
+    pub fn size(&self) -> usize {
+        match self {
+            RegisterValue::U8(_) | RegisterValue::I8(_) => 1,
+            RegisterValue::U16(_) | RegisterValue::I16(_) => 2,
+            RegisterValue::U32(_) | RegisterValue::I32(_) | RegisterValue::F32(_) => 4,
+            RegisterValue::U64(_) | RegisterValue::I64(_) | RegisterValue::F64(_) => 8,
+            RegisterValue::Byte64(_) => 8,
+            RegisterValue::F80(_) => 10,
+            RegisterValue::Byte128(_) => 16,
+        }
+    }
+
+    /// Widens the value to exactly `info.size` bytes following the rules on [`RegisterValue`],
+    /// or explains why it cannot be written to `info`.
+    pub fn to_register_bytes(&self, info: &Register) -> Result<Vec<u8>> {
+        if self.size() > info.size {
+            return Err(Error::InvalidRegister(format!(
+                "{} bytes value does not fit in {} ({} bytes)",
+                self.size(), info.name, info.size
+            )));
+        }
+        let mismatch = || Error::InvalidRegister(format!("{} cannot hold a {} value", info.name, self.kind()));
+        let mut bytes = match (info.register_format, *self) {
+            (RegisterFormat::Uint, RegisterValue::U8(v)) => (v as u64).to_le_bytes().to_vec(),
+            (RegisterFormat::Uint, RegisterValue::U16(v)) => (v as u64).to_le_bytes().to_vec(),
+            (RegisterFormat::Uint, RegisterValue::U32(v)) => (v as u64).to_le_bytes().to_vec(),
+            (RegisterFormat::Uint, RegisterValue::U64(v)) => v.to_le_bytes().to_vec(),
+            (RegisterFormat::Uint, RegisterValue::I8(v)) => (v as i64).to_le_bytes().to_vec(),
+            (RegisterFormat::Uint, RegisterValue::I16(v)) => (v as i64).to_le_bytes().to_vec(),
+            (RegisterFormat::Uint, RegisterValue::I32(v)) => (v as i64).to_le_bytes().to_vec(),
+            (RegisterFormat::Uint, RegisterValue::I64(v)) => v.to_le_bytes().to_vec(),
+            (RegisterFormat::DoubleFloat, RegisterValue::F32(v)) => (v as f64).to_le_bytes().to_vec(),
+            (RegisterFormat::DoubleFloat, RegisterValue::F64(v)) => v.to_le_bytes().to_vec(),
+            (RegisterFormat::LongDouble, RegisterValue::F32(v)) => F80::from_f64(v as f64).0.to_vec(),
+            (RegisterFormat::LongDouble, RegisterValue::F64(v)) => F80::from_f64(v).0.to_vec(),
+            (RegisterFormat::LongDouble, RegisterValue::F80(v)) => v.0.to_vec(),
+            (RegisterFormat::Vector, RegisterValue::Byte64(v)) => v.to_vec(),
+            (RegisterFormat::Vector, RegisterValue::Byte128(v)) => v.to_vec(),
+            _ => return Err(mismatch()),
+        };
+        bytes.resize(info.size, 0);
+        Ok(bytes)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            RegisterValue::U8(_) | RegisterValue::U16(_) | RegisterValue::U32(_) | RegisterValue::U64(_) => "unsigned integer",
+            RegisterValue::I8(_) | RegisterValue::I16(_) | RegisterValue::I32(_) | RegisterValue::I64(_) => "signed integer",
+            RegisterValue::F32(_) | RegisterValue::F64(_) | RegisterValue::F80(_) => "floating point",
+            RegisterValue::Byte64(_) | RegisterValue::Byte128(_) => "vector",
+        }
+    }
+}
+
+impl fmt::Display for RegisterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterValue::U8(v) => write!(f, "{:#04x}", v),
+            RegisterValue::U16(v) => write!(f, "{:#06x}", v),
+            RegisterValue::U32(v) => write!(f, "{:#010x}", v),
+            RegisterValue::U64(v) => write!(f, "{:#018x}", v),
+            RegisterValue::I8(v) => write!(f, "{}", v),
+            RegisterValue::I16(v) => write!(f, "{}", v),
+            RegisterValue::I32(v) => write!(f, "{}", v),
+            RegisterValue::I64(v) => write!(f, "{}", v),
+            RegisterValue::F32(v) => write!(f, "{}", v),
+            RegisterValue::F64(v) => write!(f, "{}", v),
+            RegisterValue::F80(v) => write!(f, "{}", v.to_f64()),
+            RegisterValue::Byte64(v) => write_bytes(f, v),
+            RegisterValue::Byte128(v) => write_bytes(f, v),
+        }
+    }
+}
+
+fn write_bytes(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    let bytes: Vec<String> = bytes.iter().map(|b| format!("{:#04x}", b)).collect();
+    write!(f, "[{}]", bytes.join(","))
+}
+
+macro_rules! impl_from {
+    ($($ty:ty => $variant:ident),*) => {
+        $(impl From<$ty> for RegisterValue {
+            fn from(value: $ty) -> Self {
+                RegisterValue::$variant(value)
+            }
+        })*
+    };
+}
+
+impl_from!(
+    u8 => U8, u16 => U16, u32 => U32, u64 => U64,
+    i8 => I8, i16 => I16, i32 => I32, i64 => I64,
+    f32 => F32, f64 => F64, F80 => F80,
+    [u8; 8] => Byte64, [u8; 16] => Byte128
+);
+
+impl F80 {
+    /// Converts an f64 to the 80-bit format, this conversion is exact.
+    pub fn from_f64(value: f64) -> Self {
+        let bits = value.to_bits();
+        let sign = ((bits >> 63) as u16) << 15;
+        let exponent = ((bits >> 52) & 0x7ff) as i32;
+        let fraction = bits & ((1 << 52) - 1);
+
+        let (exponent, mantissa) = match (exponent, fraction) {
+            (0, 0) => (0, 0),
+            (0x7ff, _) => (0x7fff, 1 << 63 | fraction << 11),
+            (0, _) => {
+                // f64 subnormals are normal numbers in the wider exponent range of the 80-bit format
+                let shift = fraction.leading_zeros();
+                (63 - 1074 - shift as i32 + 16383, fraction << shift)
+            }
+            _ => (exponent - 1023 + 16383, 1 << 63 | fraction << 11),
+        };
+
+        let mut bytes = [0u8; 10];
+        bytes[0..8].copy_from_slice(&mantissa.to_le_bytes());
+        bytes[8..10].copy_from_slice(&(sign | exponent as u16).to_le_bytes());
+        F80(bytes)
+    }
+
+    /// Converts to an f64, precision beyond 53 bits of mantissa is lost.
+    pub fn to_f64(self) -> f64 {
+        let mantissa = u64::from_le_bytes(self.0[0..8].try_into().unwrap());
+        let sign_exponent = u16::from_le_bytes(self.0[8..10].try_into().unwrap());
+        let sign = if sign_exponent & 0x8000 != 0 { -1.0 } else { 1.0 };
+        let exponent = (sign_exponent & 0x7fff) as i32;
+
+        if exponent == 0x7fff {
+            return if mantissa << 1 == 0 { sign * f64::INFINITY } else { f64::NAN };
+        }
+        // the integer bit is explicit in the 80-bit format so the mantissa reads as 1.xxx * 2^63
+        let mut value = mantissa as f64 / 2f64.powi(63);
+        // scale in steps, 2^exponent alone can over or underflow even when the result fits an f64
+        let mut exponent = exponent - 16383;
+        while exponent > 1000 && value.is_finite() {
+            value *= 2f64.powi(1000);
+            exponent -= 1000;
+        }
+        while exponent < -1000 && value != 0.0 {
+            value *= 2f64.powi(-1000);
+            exponent += 1000;
+        }
+        sign * value * 2f64.powi(exponent)
+    }
+}
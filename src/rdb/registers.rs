@@ -0,0 +1,159 @@
+use std::ffi::c_void;
+use std::mem::{offset_of, size_of};
+use std::ptr;
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::ptrace;
+use nix::unistd::Pid;
+use crate::error::{Error, Result};
+use crate::rdb::register_info::{Register, RegisterId, RegisterType, User, REGISTERS};
+use crate::rdb::register_value::RegisterValue;
+
+/// Plain old data that can be read straight out of the register file.
+///
+/// # Safety
+/// Every bit pattern of the implementing type has to be a valid value.
+pub unsafe trait Pod: Copy {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<const N: usize> Pod for [u8; N] {}
+
+/// Cached copy of the inferior's `struct user`.
+///
+/// Reads are served from the cache, which is refreshed by [`Registers::read_all`] every time
+/// the process stops. Writes update the cache and are pushed to the inferior straight away.
+pub struct Registers {
+    pid: Pid,
+    data: User,
+}
+
+impl Registers {
+    pub fn new(pid: Pid) -> Self {
+        Self {
+            pid,
+            // struct user only holds integers, arrays and raw pointers so all zeroes is valid
+            data: unsafe { std::mem::zeroed() },
+        }
+    }
+
+    /// Pulls the GPRs, the FPU/SSE state and the debug registers out of the inferior.
+    pub fn read_all(&mut self) -> Result<()> {
+        let regs = ptrace::getregs(self.pid).map_err(Error::ptrace("PTRACE_GETREGS", self.pid))?;
+        self.copy_in(offset_of!(User, regs), &regs);
+
+        let mut fpregs: libc::user_fpregs_struct = unsafe { std::mem::zeroed() };
+        let res = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETFPREGS,
+                self.pid.as_raw(),
+                ptr::null_mut::<c_void>(),
+                &mut fpregs as *mut libc::user_fpregs_struct as *mut c_void,
+            )
+        };
+        Errno::result(res).map_err(Error::ptrace("PTRACE_GETFPREGS", self.pid))?;
+        self.copy_in(offset_of!(User, i387), &fpregs);
+
+        for info in REGISTERS.iter().filter(|r| r.register_type == RegisterType::Dr) {
+            let value = ptrace::read_user(self.pid, info.offset as ptrace::AddressType)
+                .map_err(Error::ptrace("PTRACE_PEEKUSER", self.pid))?;
+            self.copy_in(info.offset, &(value as u64));
+        }
+        Ok(())
+    }
+
+    /// Reads a register as `T`, `T` may be narrower than the register in which case
+    /// only the low bytes are returned.
+    pub fn read_by_id_as<T: Pod>(&self, id: RegisterId) -> T {
+        let info = Register::by_id(id);
+        assert!(
+            size_of::<T>() <= info.size,
+            "{} is {} bytes wide, cannot read {} bytes out of it",
+            info.name, info.size, size_of::<T>()
+        );
+        let bytes = &self.bytes()[info.offset..info.offset + size_of::<T>()];
+        unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) }
+    }
+
+    /// Raw little endian bytes of the register, always `info.size` long.
+    pub fn read_bytes(&self, info: &Register) -> &[u8] {
+        &self.bytes()[info.offset..info.offset + info.size]
+    }
+
+    pub fn read(&self, info: &Register) -> RegisterValue {
+        RegisterValue::from_bytes(info, self.read_bytes(info))
+    }
+
+    pub fn read_by_id(&self, id: RegisterId) -> RegisterValue {
+        self.read(Register::by_id(id))
+    }
+
+    /// Writes `value` into the register, see [`RegisterValue`] for how values narrower than
+    /// the register are widened and which combinations are rejected.
+    pub fn write(&mut self, info: &Register, value: RegisterValue) -> Result<()> {
+        let bytes = value.to_register_bytes(info)?;
+        self.bytes_mut()[info.offset..info.offset + info.size].copy_from_slice(&bytes);
+        self.flush(info)
+    }
+
+    pub fn write_by_id(&mut self, id: RegisterId, value: impl Into<RegisterValue>) -> Result<()> {
+        self.write(Register::by_id(id), value.into())
+    }
+
+    /// Pushes the part of the cache that holds `info` back into the inferior.
+    fn flush(&mut self, info: &Register) -> Result<()> {
+        match info.register_type {
+            RegisterType::Gpr | RegisterType::SubGpr => {
+                let regs: libc::user_regs_struct = self.copy_out(offset_of!(User, regs));
+                ptrace::setregs(self.pid, regs).map_err(Error::ptrace("PTRACE_SETREGS", self.pid))
+            }
+            RegisterType::Fpr => {
+                let fpregs: libc::user_fpregs_struct = self.copy_out(offset_of!(User, i387));
+                let res = unsafe {
+                    libc::ptrace(
+                        libc::PTRACE_SETFPREGS,
+                        self.pid.as_raw(),
+                        ptr::null_mut::<c_void>(),
+                        &fpregs as *const libc::user_fpregs_struct as *mut c_void,
+                    )
+                };
+                Errno::result(res)
+                    .map(drop)
+                    .map_err(Error::ptrace("PTRACE_SETFPREGS", self.pid))
+            }
+            RegisterType::Dr => {
+                // POKEUSER wants an 8 byte aligned offset
+                let aligned = info.offset & !0b111;
+                let value: u64 = self.copy_out(aligned);
+                ptrace::write_user(self.pid, aligned as ptrace::AddressType, value as libc::c_long)
+                    .map_err(Error::ptrace("PTRACE_POKEUSER", self.pid))
+            }
+        }
+    }
+
+    fn copy_in<T>(&mut self, offset: usize, value: &T) {
+        let src = unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) };
+        self.bytes_mut()[offset..offset + size_of::<T>()].copy_from_slice(src);
+    }
+
+    fn copy_out<T: Copy>(&self, offset: usize) -> T {
+        let bytes = &self.bytes()[offset..offset + size_of::<T>()];
+        unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(&self.data as *const User as *const u8, size_of::<User>()) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(&mut self.data as *mut User as *mut u8, size_of::<User>()) }
+    }
+}
@@ -0,0 +1,81 @@
+use nix::libc;
+use nix::sys::ptrace;
+use nix::sys::signal::Signal;
+use nix::sys::wait::WaitStatus;
+use crate::rdb::process::ProcessState;
+
+/// Why the inferior is no longer running, built from the `waitpid` status and, for SIGTRAP,
+/// refined with PTRACE_GETSIGINFO and the debug status register.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StopReason {
+    pub state: ProcessState,
+    pub info: StopInfo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopInfo {
+    /// The process exited normally with this status.
+    ExitCode(i32),
+    /// The process was stopped or terminated by this signal.
+    Signal(Signal),
+    /// The process stopped with SIGTRAP for this reason.
+    Trap(TrapKind),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TrapKind {
+    SoftwareBreakpoint(i32),
+    HardwareBreakpoint(i32),
+    /// The watched value as it was before and after the write, taken when the thread stopped.
+    Watchpoint { id: i32, old_value: u64, new_value: u64 },
+    SingleStep,
+    Syscall,
+    Clone,
+    Exec,
+    Fork,
+    Vfork,
+    /// PTRACE_INTERRUPT stopped a seized process, no signal was involved.
+    Interrupt,
+    /// A SIGTRAP none of our stoppoints account for, e.g. an `int3` compiled into the program.
+    Unknown,
+}
+
+impl StopReason {
+    /// Translates the raw wait status, the caller fills in the trap kind for SIGTRAP stops.
+    pub(crate) fn from_wait_status(status: WaitStatus) -> Self {
+        let (state, info) = match status {
+            WaitStatus::Exited(_, code) => (ProcessState::Exited, StopInfo::ExitCode(code)),
+            WaitStatus::Signaled(_, signal, _) => (ProcessState::Terminated, StopInfo::Signal(signal)),
+            WaitStatus::Stopped(_, signal) => (ProcessState::Stopped, StopInfo::Signal(signal)),
+            WaitStatus::PtraceSyscall(_) => (ProcessState::Stopped, StopInfo::Trap(TrapKind::Syscall)),
+            // a seized process reports group-stops as PTRACE_EVENT_STOP with the stopping signal,
+            // and PTRACE_INTERRUPT as the same event with SIGTRAP
+            WaitStatus::PtraceEvent(_, signal, event) if event == libc::PTRACE_EVENT_STOP => {
+                match signal {
+                    Signal::SIGTRAP => (ProcessState::Stopped, StopInfo::Trap(TrapKind::Interrupt)),
+                    signal => (ProcessState::Stopped, StopInfo::Signal(signal)),
+                }
+            }
+            WaitStatus::PtraceEvent(_, _, event) => {
+                let kind = match event {
+                    e if e == ptrace::Event::PTRACE_EVENT_CLONE as i32 => TrapKind::Clone,
+                    e if e == ptrace::Event::PTRACE_EVENT_EXEC as i32 => TrapKind::Exec,
+                    e if e == ptrace::Event::PTRACE_EVENT_FORK as i32 => TrapKind::Fork,
+                    e if e == ptrace::Event::PTRACE_EVENT_VFORK as i32 => TrapKind::Vfork,
+                    _ => TrapKind::Unknown,
+                };
+                (ProcessState::Stopped, StopInfo::Trap(kind))
+            }
+            // Continued and StillAlive never come back from a blocking waitpid without WCONTINUED
+            _ => (ProcessState::Running, StopInfo::Signal(Signal::SIGCONT)),
+        };
+        Self { state, info }
+    }
+
+    pub fn is_breakpoint(&self) -> bool {
+        matches!(
+            self.info,
+            StopInfo::Trap(TrapKind::SoftwareBreakpoint(_) | TrapKind::HardwareBreakpoint(_))
+        )
+    }
+}
@@ -0,0 +1,92 @@
+use crate::rdb::address::VirtAddr;
+
+/// Anything that stops the inferior at an address: breakpoint sites and, later on, watchpoints.
+pub trait Stoppoint {
+    fn id(&self) -> i32;
+    fn address(&self) -> VirtAddr;
+    fn is_enabled(&self) -> bool;
+}
+
+/// Owns a set of stoppoints and hands out their ids, ids start at 1 and are never reused.
+/// Stoppoints rdb sets for itself count down from -1 so they never take an id from the user.
+#[derive(Clone)]
+pub struct StoppointCollection<T: Stoppoint> {
+    stoppoints: Vec<T>,
+    next_id: i32,
+    next_internal_id: i32,
+}
+
+impl<T: Stoppoint> Default for StoppointCollection<T> {
+    fn default() -> Self {
+        Self { stoppoints: Vec::new(), next_id: 1, next_internal_id: -1 }
+    }
+}
+
+impl<T: Stoppoint> StoppointCollection<T> {
+    pub fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn next_internal_id(&mut self) -> i32 {
+        let id = self.next_internal_id;
+        self.next_internal_id -= 1;
+        id
+    }
+
+    pub fn push(&mut self, stoppoint: T) -> &mut T {
+        self.stoppoints.push(stoppoint);
+        self.stoppoints.last_mut().unwrap()
+    }
+
+    pub fn contains_id(&self, id: i32) -> bool {
+        self.stoppoints.iter().any(|s| s.id() == id)
+    }
+
+    pub fn contains_address(&self, address: VirtAddr) -> bool {
+        self.stoppoints.iter().any(|s| s.address() == address)
+    }
+
+    pub fn enabled_stoppoint_at_address(&self, address: VirtAddr) -> bool {
+        self.get_by_address(address).is_some_and(|s| s.is_enabled())
+    }
+
+    pub fn get_by_id(&self, id: i32) -> Option<&T> {
+        self.stoppoints.iter().find(|s| s.id() == id)
+    }
+
+    pub fn get_by_id_mut(&mut self, id: i32) -> Option<&mut T> {
+        self.stoppoints.iter_mut().find(|s| s.id() == id)
+    }
+
+    pub fn get_by_address(&self, address: VirtAddr) -> Option<&T> {
+        self.stoppoints.iter().find(|s| s.address() == address)
+    }
+
+    pub fn get_by_address_mut(&mut self, address: VirtAddr) -> Option<&mut T> {
+        self.stoppoints.iter_mut().find(|s| s.address() == address)
+    }
+
+    /// Takes the stoppoint out of the collection, the caller is responsible for disabling it first.
+    pub fn remove_by_id(&mut self, id: i32) -> Option<T> {
+        let index = self.stoppoints.iter().position(|s| s.id() == id)?;
+        Some(self.stoppoints.remove(index))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.stoppoints.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.stoppoints.iter_mut()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stoppoints.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stoppoints.len()
+    }
+}
@@ -0,0 +1,77 @@
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use crate::rdb::address::VirtAddr;
+use crate::rdb::process::ProcessState;
+use crate::rdb::register_info::RegisterId;
+use crate::rdb::registers::Registers;
+use crate::rdb::stop_reason::{StopInfo, StopReason};
+
+/// One thread of the inferior. Debugging is all-stop: whenever one thread stops the others are
+/// stopped as well, and resuming resumes all of them.
+pub struct Thread {
+    id: usize,
+    tid: Pid,
+    pub(crate) state: ProcessState,
+    /// Why the thread last stopped, `None` when we stopped it because another thread did.
+    pub(crate) stop_reason: Option<StopReason>,
+    /// `stop_reason` was collected while stopping the threads for someone else's stop and still
+    /// has to be reported. Resuming reports it instead of running anything.
+    pub(crate) pending: bool,
+    /// We sent SIGSTOP or PTRACE_INTERRUPT, or the kernel did for a new thread, and the stop it
+    /// causes has not been seen yet. That stop is ours and never reported.
+    pub(crate) stop_requested: bool,
+    /// Created by a clone after our hardware stoppoints were set, the debug registers still
+    /// have to be copied over once it first stops.
+    pub(crate) needs_debug_registers: bool,
+    pub(crate) registers: Registers,
+}
+
+impl Thread {
+    pub(crate) fn new(id: usize, tid: Pid) -> Self {
+        Self {
+            id,
+            tid,
+            state: ProcessState::Running,
+            stop_reason: None,
+            pending: false,
+            stop_requested: false,
+            needs_debug_registers: false,
+            registers: Registers::new(tid),
+        }
+    }
+
+    /// Numbered from 1 in the order we learn about the threads, ids are never reused.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn tid(&self) -> Pid {
+        self.tid
+    }
+
+    pub fn state(&self) -> ProcessState {
+        self.state
+    }
+
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    /// The signal the thread stopped with, which it still has to get when it runs again. Not
+    /// SIGTRAP, which our breakpoints and steps cause, nor SIGSTOP, which we send ourselves.
+    pub(crate) fn pending_signal(&self) -> Option<Signal> {
+        match self.stop_reason {
+            Some(StopReason { info: StopInfo::Signal(signal), .. })
+                if signal != Signal::SIGSTOP && signal != Signal::SIGTRAP => Some(signal),
+            _ => None,
+        }
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn get_pc(&self) -> VirtAddr {
+        VirtAddr::new(self.registers.read_by_id_as::<u64>(RegisterId::Rip))
+    }
+}
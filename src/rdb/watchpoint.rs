@@ -0,0 +1,106 @@
+use crate::error::{Error, Result};
+use crate::rdb::address::VirtAddr;
+use crate::rdb::stoppoint_collection::Stoppoint;
+
+/// What kind of access trips a hardware stoppoint, these map directly onto the R/W bits of DR7.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StoppointMode {
+    Execute,
+    Write,
+    ReadWrite,
+}
+
+impl StoppointMode {
+    pub fn parse(text: &str) -> Result<Self> {
+        match text {
+            "execute" | "x" => Ok(StoppointMode::Execute),
+            "write" | "w" => Ok(StoppointMode::Write),
+            "read_write" | "rw" => Ok(StoppointMode::ReadWrite),
+            _ => Err(Error::Parse(format!("Invalid watchpoint mode, expected write, rw or execute: {}", text))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StoppointMode::Execute => "execute",
+            StoppointMode::Write => "write",
+            StoppointMode::ReadWrite => "read_write",
+        }
+    }
+}
+
+/// A hardware data watchpoint over `size` bytes at `address`.
+///
+/// The watched value is sampled when the watchpoint is created and every time it fires, so a
+/// hit can report what the memory held before and after.
+#[derive(Clone)]
+pub struct Watchpoint {
+    id: i32,
+    address: VirtAddr,
+    mode: StoppointMode,
+    size: usize,
+    hardware_slot: Option<usize>,
+    data: u64,
+    previous_data: u64,
+}
+
+impl Stoppoint for Watchpoint {
+    fn id(&self) -> i32 {
+        self.id
+    }
+    fn address(&self) -> VirtAddr {
+        self.address
+    }
+    fn is_enabled(&self) -> bool {
+        self.hardware_slot.is_some()
+    }
+}
+
+impl Watchpoint {
+    /// Fails unless `size` is one the debug registers support for `mode` and `address` is aligned
+    /// to it.
+    pub(crate) fn new(id: i32, address: VirtAddr, mode: StoppointMode, size: usize) -> Result<Self> {
+        if ![1, 2, 4, 8].contains(&size) {
+            return Err(Error::Stoppoint(format!("Invalid watchpoint size {}, expected 1, 2, 4 or 8", size)));
+        }
+        // execute breakpoints must use LEN 00, other lengths are undefined
+        if mode == StoppointMode::Execute && size != 1 {
+            return Err(Error::Stoppoint(format!("Invalid execute watchpoint size {}, expected 1", size)));
+        }
+        if address.addr() & (size as u64 - 1) != 0 {
+            return Err(Error::Stoppoint(format!(
+                "Watchpoint at {:#x} must be aligned to its size ({} bytes)", address, size
+            )));
+        }
+        Ok(Self { id, address, mode, size, hardware_slot: None, data: 0, previous_data: 0 })
+    }
+
+    pub fn mode(&self) -> StoppointMode {
+        self.mode
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn hardware_slot(&self) -> Option<usize> {
+        self.hardware_slot
+    }
+
+    pub fn data(&self) -> u64 {
+        self.data
+    }
+
+    pub fn previous_data(&self) -> u64 {
+        self.previous_data
+    }
+
+    pub(crate) fn set_hardware_slot(&mut self, slot: Option<usize>) {
+        self.hardware_slot = slot;
+    }
+
+    pub(crate) fn update_data(&mut self, data: u64) {
+        self.previous_data = self.data;
+        self.data = data;
+    }
+}
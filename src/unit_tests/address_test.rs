@@ -0,0 +1,41 @@
+use crate::rdb::address::{FileAddr, FileOffset, VirtAddr};
+use crate::rdb::elf::Elf;
+
+#[test]
+fn addresses_convert_through_load_bias(){
+    let mut elf = Elf::open("/proc/self/exe").unwrap();
+    elf.set_load_bias(0x1000_0000);
+    let main = FileAddr::new(&elf, elf.function("main").unwrap().value);
+    let virt = main.to_virt_addr();
+    assert_eq!(virt - VirtAddr::new(main.addr()), 0x1000_0000);
+    assert_eq!(virt.to_file_addr(&elf), Some(main));
+    assert_eq!(elf.symbol_at_virt_addr(virt + 1).map(|l| l.offset), Some(1));
+    assert_eq!(VirtAddr::new(main.addr()).to_file_addr(&elf), None);
+
+    let offset = main.to_file_offset().unwrap();
+    assert_eq!(offset.to_file_addr(), Some(main));
+    let text = elf.section(".text").unwrap();
+    assert_eq!(offset.off() - text.sh_offset, main.addr() - text.sh_addr);
+    assert_eq!(FileOffset::new(&elf, 0).to_file_addr().map(|a| a.to_file_offset()), Some(Some(FileOffset::new(&elf, 0))));
+}
+
+#[test]
+fn file_addresses_of_different_objects_differ(){
+    let first = Elf::open("/proc/self/exe").unwrap();
+    let second = Elf::open("/proc/self/exe").unwrap();
+    assert_ne!(FileAddr::new(&first, 0x1000), FileAddr::new(&second, 0x1000));
+    assert_eq!(FileAddr::new(&first, 0x1000).partial_cmp(&FileAddr::new(&second, 0x1000)), None);
+    assert!(FileAddr::new(&first, 0x1000) < FileAddr::new(&first, 0x1000) + 1);
+}
+
+#[test]
+fn file_address_of_another_object_finds_nothing(){
+    let first = Elf::open("/proc/self/exe").unwrap();
+    let second = Elf::open("/proc/self/exe").unwrap();
+    let main = FileAddr::new(&second, second.function("main").unwrap().value);
+    assert!(second.symbol_containing(main).is_some());
+    assert!(first.symbol_containing(main).is_none());
+    assert!(first.section_containing(main).is_none());
+    assert!(first.line_entry_at(main).is_none());
+    assert!(first.function_frames_at(main).is_empty());
+}
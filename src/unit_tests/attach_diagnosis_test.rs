@@ -0,0 +1,54 @@
+use nix::errno::Errno;
+use crate::error::Error;
+use crate::rdb::attach_diagnosis::{caller_uid, explain, AttachDenial, AttachFacts};
+
+fn facts() -> AttachFacts {
+    AttachFacts {
+        exists: true,
+        state: Some('S'),
+        our_uid: Some(1000),
+        their_uids: vec![1000, 1000, 1000],
+        ptrace_scope: Some(1),
+        ..AttachFacts::default()
+    }
+}
+
+#[test]
+fn explain_uid_mismatch_unless_privileged(){
+    let setuid = AttachFacts { their_uids: vec![1000, 0, 0], ..facts() };
+    assert_eq!(
+        explain(42, Errno::EPERM, &setuid),
+        Error::AttachDenied { pid: 42, reason: AttachDenial::DifferentUser { ours: 1000, theirs: 0 } }
+    );
+    let privileged = AttachFacts { has_cap_sys_ptrace: true, ..setuid };
+    assert_eq!(explain(42, Errno::EPERM, &privileged), Error::Attach { pid: 42, errno: Errno::EPERM });
+}
+
+#[test]
+fn explain_uid_mismatch_with_our_real_uid(){
+    // a setuid root rdb run by uid 1000 still only gets at uid 1000's processes
+    let ours = "Name:\trdb\nUid:\t1000\t0\t0\t0\nGid:\t1000\t1000\t1000\t1000\n";
+    assert_eq!(caller_uid(ours), Some(1000));
+    let root_target = AttachFacts { our_uid: caller_uid(ours), their_uids: vec![0, 0, 0], ..facts() };
+    assert_eq!(
+        explain(42, Errno::EPERM, &root_target),
+        Error::AttachDenied { pid: 42, reason: AttachDenial::DifferentUser { ours: 1000, theirs: 0 } }
+    );
+    assert_eq!(caller_uid("Name:\trdb\n"), None);
+}
+
+#[test]
+fn explain_ptrace_scope(){
+    let scope = |scope, is_descendant| AttachFacts { ptrace_scope: Some(scope), is_descendant, ..facts() };
+    let denied = |scope| Error::AttachDenied { pid: 42, reason: AttachDenial::PtraceScope(scope) };
+    assert_eq!(explain(42, Errno::EPERM, &scope(1, false)), denied(1));
+    // the process may have allowed us with PR_SET_PTRACER, which /proc does not show
+    let message = denied(1).to_string();
+    assert!(message.contains("likely") && message.contains("PR_SET_PTRACER"), "{}", message);
+    assert_eq!(explain(42, Errno::EPERM, &scope(1, true)), Error::Attach { pid: 42, errno: Errno::EPERM });
+    assert_eq!(explain(42, Errno::EPERM, &scope(2, true)), denied(2));
+    let locked = AttachFacts { has_cap_sys_ptrace: true, ..scope(3, true) };
+    assert_eq!(explain(42, Errno::EPERM, &locked), denied(3));
+    let no_yama = AttachFacts { ptrace_scope: None, ..facts() };
+    assert_eq!(explain(42, Errno::EPERM, &no_yama), Error::Attach { pid: 42, errno: Errno::EPERM });
+}
@@ -0,0 +1,50 @@
+use crate::error::Error;
+use crate::rdb::address::FileAddr;
+use crate::rdb::elf::{demangle, Elf, SHT_NOBITS};
+
+#[test]
+fn elf_parses_own_executable(){
+    let elf = Elf::open("/proc/self/exe").unwrap();
+    assert_eq!(elf.path(), std::env::current_exe().unwrap());
+    assert!(!elf.program_headers().is_empty());
+    let text = elf.section(".text").unwrap();
+    assert_eq!(elf.section_contents(".text").unwrap().len(), text.sh_size as usize);
+    assert!(std::ptr::eq(elf.section_containing(elf.entry()).unwrap(), text));
+    assert_eq!(elf.section(".bss").map(|s| s.sh_type), Some(SHT_NOBITS));
+    assert_eq!(elf.section_contents(".bss"), Some(&[][..]));
+
+    let main = elf.function("main").unwrap();
+    assert_eq!(main.name, "main");
+    assert!(std::ptr::eq(elf.section_containing(FileAddr::new(&elf, main.value)).unwrap(), text));
+    assert!(elf.symbols_by_name("no such symbol").next().is_none());
+}
+
+#[test]
+fn elf_rejects_other_files(){
+    let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+    assert!(matches!(Elf::open(manifest), Err(Error::Elf { message, .. }) if message == "Not an ELF file"));
+    assert!(matches!(Elf::open("/nonexistent"), Err(Error::Elf { .. })));
+}
+
+#[test]
+fn elf_finds_symbols_by_address(){
+    let elf = Elf::open("/proc/self/exe").unwrap();
+    let main = elf.function("main").unwrap();
+    let location = elf.symbol_containing(FileAddr::new(&elf, main.value)).unwrap();
+    assert_eq!((location.symbol.name.as_str(), location.offset), ("main", 0));
+    assert_eq!(location.to_string(), "main");
+    if main.size > 1 {
+        let location = elf.symbol_containing(FileAddr::new(&elf, main.value) + 1).unwrap();
+        assert_eq!(location.to_string(), "main+0x1");
+    }
+    assert!(elf.symbol_containing(FileAddr::new(&elf, 0)).is_none());
+}
+
+#[test]
+fn demangle_handles_rust_and_cpp(){
+    assert_eq!(demangle("_ZN4core3fmt5write17h0123456789abcdefE"), "core::fmt::write");
+    assert_eq!(demangle("_RNvCs1234_7mycrate3foo"), "mycrate::foo");
+    assert_eq!(demangle("_ZN2ns3fooEi"), "ns::foo(int)");
+    assert_eq!(demangle("main"), "main");
+    assert_eq!(demangle("_Znot_mangled"), "_Znot_mangled");
+}
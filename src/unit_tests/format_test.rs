@@ -0,0 +1,42 @@
+use crate::utils::format::{hexdump, DisplayFormat, ExamineFormat};
+
+#[test]
+fn examine_format_defaults_and_letters(){
+    let format = ExamineFormat::parse("").unwrap();
+    assert_eq!((format.count, format.unit_size, format.format), (1, 4, DisplayFormat::Hex));
+    let format = ExamineFormat::parse("8gx").unwrap();
+    assert_eq!((format.count, format.unit_size, format.format), (8, 8, DisplayFormat::Hex));
+    let format = ExamineFormat::parse("4c").unwrap();
+    assert_eq!((format.count, format.unit_size, format.format), (4, 1, DisplayFormat::Char));
+    let format = ExamineFormat::parse("2f").unwrap();
+    assert_eq!((format.count, format.unit_size, format.format), (2, 8, DisplayFormat::Float));
+    assert!(ExamineFormat::parse("2bf").is_err());
+    assert!(ExamineFormat::parse("0x").is_err());
+    assert!(ExamineFormat::parse("3q").is_err());
+}
+
+#[test]
+fn examine_format_rows(){
+    let bytes: Vec<u8> = (1..=24).collect();
+    let format = ExamineFormat::parse("3gx").unwrap();
+    assert_eq!(format.format_units(0x1000, &bytes), vec![
+        "0x0000000000001000:\t0x0807060504030201\t0x100f0e0d0c0b0a09",
+        "0x0000000000001010:\t0x1817161514131211",
+    ]);
+
+    let format = ExamineFormat::parse("2hd").unwrap();
+    assert_eq!(format.format_units(0x10, &[0xff, 0xff, 0x02, 0x00]), vec!["0x0000000000000010:\t-1\t2"]);
+    let format = ExamineFormat::parse("2c").unwrap();
+    assert_eq!(format.format_units(0x10, b"h\n"), vec!["0x0000000000000010:\t104 'h'\t10 '\\n'"]);
+    let format = ExamineFormat::parse("wf").unwrap();
+    assert_eq!(format.format_units(0x10, &1.5f32.to_le_bytes()), vec!["0x0000000000000010:\t1.5"]);
+}
+
+#[test]
+fn hexdump_pads_the_last_row(){
+    let bytes: Vec<u8> = b"Hello, world!\n\x00\x01ab".to_vec();
+    assert_eq!(hexdump(0x401000, &bytes), vec![
+        "0x0000000000401000:  48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 0a 00 01  |Hello, world!...|",
+        "0x0000000000401010:  61 62                                            |ab|",
+    ]);
+}
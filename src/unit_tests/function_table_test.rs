@@ -0,0 +1,21 @@
+use crate::rdb::address::FileAddr;
+use crate::rdb::elf::Elf;
+use crate::rdb::function_table::ReturnType;
+
+fn answer() -> u32 {
+    42
+}
+
+#[test]
+fn function_table_of_own_executable(){
+    assert_eq!(answer(), 42);
+    let elf = Elf::open("/proc/self/exe").unwrap();
+    let symbol = elf.symbols_by_name("rdb::unit_tests::function_table_test::answer").next().unwrap();
+    let frames = elf.function_frames_at(FileAddr::new(&elf, symbol.value));
+    let function = frames.last().expect("no function covers answer");
+    assert_eq!(function.name, "answer");
+    assert_eq!(function.entry, symbol.value);
+    assert!(!function.is_inlined());
+    assert_eq!(function.return_type, ReturnType::Integer { size: 4, signed: false });
+    assert!(elf.function_frames_at(FileAddr::new(&elf, 0)).is_empty());
+}
@@ -0,0 +1,22 @@
+use crate::rdb::interval_index::IntervalIndex;
+
+#[test]
+fn interval_index_finds_innermost_range(){
+    let index = IntervalIndex::new([(0x100..0x200, "outer"), (0x140..0x150, "inner"), (0x300..0x300, "empty"), (0x180..0x190, "second")]);
+    assert_eq!(index.len(), 3);
+    assert_eq!(index.find(0xff), None);
+    assert_eq!(index.find(0x100), Some(&"outer"));
+    assert_eq!(index.find(0x145), Some(&"inner"));
+    assert_eq!(index.find(0x150), Some(&"outer"));
+    assert_eq!(index.find(0x18f), Some(&"second"));
+    assert_eq!(index.find(0x200), None);
+    assert_eq!(index.find(0x300), None);
+}
+
+#[test]
+fn interval_index_prefers_last_added_of_equal_ranges(){
+    let index = IntervalIndex::new([(0x10..0x20, "local"), (0x10..0x20, "global"), (0x10..0x30, "longer")]);
+    assert_eq!(index.find(0x18), Some(&"global"));
+    assert_eq!(index.find(0x28), Some(&"longer"));
+    assert!(IntervalIndex::<()>::new([]).is_empty());
+}
@@ -0,0 +1,22 @@
+use crate::rdb::debug_file::crc32;
+use crate::rdb::elf::Elf;
+
+#[test]
+fn crc32_matches_zlib(){
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+}
+
+#[test]
+fn line_table_of_own_executable(){
+    let elf = Elf::open("/proc/self/exe").unwrap();
+    let table = elf.line_table().expect("test executable has no line table");
+    assert!(!table.entries().is_empty());
+    let function = elf.symbols_by_name("rdb::unit_tests::line_table_test::line_table_of_own_executable")
+        .next()
+        .expect("test function not found");
+    let entry = table.entry_at(function.value).expect("no line for the test function");
+    assert!(entry.file.ends_with("src/unit_tests/line_table_test.rs"), "{}", entry.file.display());
+    assert_eq!(entry.line, 11);
+    assert!(table.entry_at(0).is_none());
+}
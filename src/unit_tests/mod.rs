@@ -1 +1,11 @@
-mod rdb_test;
\ No newline at end of file
+mod rdb_test;
+mod parse_test;
+mod format_test;
+mod address_test;
+mod attach_diagnosis_test;
+mod elf_test;
+mod function_table_test;
+mod interval_index_test;
+mod line_table_test;
+
+mod watchpoint_test;
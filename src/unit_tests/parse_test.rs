@@ -0,0 +1,17 @@
+use crate::utils::parse::{parse_u64, parse_vector};
+
+#[test]
+fn parse_u64_hex_and_decimal(){
+    assert_eq!(parse_u64("0x1f"), Ok(31));
+    assert_eq!(parse_u64("31"), Ok(31));
+    assert!(parse_u64("1.5").is_err());
+    assert!(parse_u64("0xzz").is_err());
+}
+
+#[test]
+fn parse_vector_round_trips_register_output(){
+    assert_eq!(parse_vector("[0x01,0xff, 0x10]"), Ok(vec![0x01, 0xff, 0x10]));
+    assert!(parse_vector("0x01,0x02").is_err());
+    assert!(parse_vector("[0x01,2]").is_err());
+    assert!(parse_vector("[0x100]").is_err());
+}
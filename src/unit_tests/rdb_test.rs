@@ -1,2 +1,68 @@
+use crate::rdb::address::VirtAddr;
+use crate::rdb::disassembler::{decode, Syntax};
+use crate::rdb::register_info::{Register, RegisterId};
+use crate::rdb::register_value::{RegisterValue, F80};
+
 #[test]
-fn empty_test(){}
\ No newline at end of file
+fn empty_test(){}
+
+#[test]
+fn f80_round_trip(){
+    for value in [0.0, -0.0, 1.0, -2.5, 42.5, f64::MAX, f64::MIN_POSITIVE, 5e-324, f64::INFINITY] {
+        assert_eq!(F80::from_f64(value).to_f64(), value);
+    }
+    assert!(F80::from_f64(f64::NAN).to_f64().is_nan());
+    // 1.0 is an explicit integer bit with the biased exponent 0x3fff
+    assert_eq!(F80::from_f64(1.0).0, [0, 0, 0, 0, 0, 0, 0, 0x80, 0xff, 0x3f]);
+}
+
+#[test]
+fn register_value_widening(){
+    let rax = Register::by_id(RegisterId::Rax);
+    let ah = Register::by_id(RegisterId::Ah);
+    let xmm0 = Register::by_id(RegisterId::Xmm0);
+
+    assert_eq!(RegisterValue::I8(-1).to_register_bytes(rax), Ok(vec![0xff; 8]));
+    assert_eq!(RegisterValue::U16(0x1234).to_register_bytes(rax), Ok(vec![0x34, 0x12, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(RegisterValue::U8(0x42).to_register_bytes(ah), Ok(vec![0x42]));
+    assert_eq!(RegisterValue::Byte64([1; 8]).to_register_bytes(xmm0).unwrap()[8..], [0; 8]);
+}
+
+#[test]
+fn register_value_rejects_mismatches(){
+    let rax = Register::by_id(RegisterId::Rax);
+    let ax = Register::by_id(RegisterId::Ax);
+    let mm0 = Register::by_id(RegisterId::Mm0);
+
+    assert!(RegisterValue::U32(0x1ffff).to_register_bytes(ax).is_err());
+    assert!(RegisterValue::F64(1.5).to_register_bytes(rax).is_err());
+    assert!(RegisterValue::Byte128([0; 16]).to_register_bytes(mm0).is_err());
+    assert!(RegisterValue::U64(1).to_register_bytes(mm0).is_err());
+}
+
+#[test]
+fn register_value_parse(){
+    let ax = Register::by_id(RegisterId::Ax);
+    let st0 = Register::by_id(RegisterId::St0);
+
+    assert_eq!(RegisterValue::parse(ax, "0xffff"), Ok(RegisterValue::U16(0xffff)));
+    assert_eq!(RegisterValue::parse(ax, "-2"), Ok(RegisterValue::I16(-2)));
+    assert!(RegisterValue::parse(ax, "0x1ffff").is_err());
+    assert!(RegisterValue::parse(ax, "1.5").is_err());
+    assert_eq!(RegisterValue::parse(st0, "42.5"), Ok(RegisterValue::F80(F80::from_f64(42.5))));
+}
+
+#[test]
+fn decode_att_and_intel(){
+    // push %rbp; mov %rsp,%rbp; call +0; then a truncated mov
+    let code = [0x55, 0x48, 0x89, 0xe5, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x48, 0x89];
+    let att = decode(&code, VirtAddr::new(0x401000), 10, Syntax::Att);
+    let texts: Vec<&str> = att.iter().map(|i| i.text.as_str()).collect();
+    assert_eq!(texts, vec!["push %rbp", "mov %rsp,%rbp", "call 0x0000000000401009"]);
+    assert_eq!(att[1].address, VirtAddr::new(0x401001));
+    assert_eq!(att[1].bytes, vec![0x48, 0x89, 0xe5]);
+
+    let intel = decode(&code, VirtAddr::new(0x401000), 2, Syntax::Intel);
+    let texts: Vec<&str> = intel.iter().map(|i| i.text.as_str()).collect();
+    assert_eq!(texts, vec!["push rbp", "mov rbp,rsp"]);
+}
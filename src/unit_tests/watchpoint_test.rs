@@ -0,0 +1,20 @@
+use crate::error::Error;
+use crate::rdb::address::VirtAddr;
+use crate::rdb::watchpoint::{StoppointMode, Watchpoint};
+
+#[test]
+fn watchpoint_checks_size_and_alignment(){
+    let address = VirtAddr::new(0x1000);
+    for size in [1, 2, 4, 8] {
+        assert!(Watchpoint::new(1, address, StoppointMode::Write, size).is_ok());
+        assert!(Watchpoint::new(1, address, StoppointMode::ReadWrite, size).is_ok());
+    }
+    assert!(matches!(Watchpoint::new(1, address, StoppointMode::Write, 3), Err(Error::Stoppoint(_))));
+    assert!(matches!(Watchpoint::new(1, address + 2, StoppointMode::Write, 4), Err(Error::Stoppoint(_))));
+
+    // execute breakpoints only have the one byte length
+    assert!(Watchpoint::new(1, address, StoppointMode::Execute, 1).is_ok());
+    for size in [2, 4, 8] {
+        assert!(matches!(Watchpoint::new(1, address, StoppointMode::Execute, size), Err(Error::Stoppoint(_))));
+    }
+}
@@ -1,13 +1,71 @@
+use std::time::Duration;
+use nix::unistd::Pid;
+use crate::error::{Error, Result};
+use crate::rdb::launch_options::{LaunchOptions, Stdio, StopAt};
 use crate::rdb::process::Process;
+use crate::utils::proc::{find_processes, wait_for_processes, ProcessEntry};
 
-pub fn attach(args: Vec<String>) -> Result<Process, String> {
+/// How often `--wait` looks for the process.
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// `rdb -p <pid>` attaches, `rdb -n <name>` attaches by name and `rdb --wait <name>` attaches to
+/// the next process of that name to start, see [`attach_by_name`]. Anything else launches:
+/// `rdb [--stdin=<to>] [--stdout=<to>] [--stderr=<to>] [--tty] [--stop-at=<stop>] <program> [--] [args...]`
+/// where `<to>` is `inherit`, `null`, `pty` or a file path and `--tty` puts all three on a pty.
+/// `<stop>` is `exec` (the default), `entry` or `main`.
+pub fn attach(args: Vec<String>, choose: impl FnOnce(&[ProcessEntry]) -> Option<Pid>) -> Result<Process> {
     // -p target_pid
     if args.len() == 3 && args[1] == "-p" {
         // attach to an existing process
-        Process::attach(&args[2])
-    } else {
-        //spin up a new process and attach to it
-        let program_path = args[1].clone();
-        Process::launch(&program_path)
+        return Process::attach(&args[2]);
+    }
+    if args.len() == 3 && (args[1] == "-n" || args[1] == "--wait") {
+        return attach_by_name(&args[2], args[1] == "--wait", choose);
     }
-}
\ No newline at end of file
+    //spin up a new process and attach to it
+    let mut args = args[1..].iter().peekable();
+    let mut streams = [Stdio::Inherit, Stdio::Inherit, Stdio::Inherit];
+    let mut stop_at = StopAt::Exec;
+    while let Some(flag) = args.next_if(|arg| arg.starts_with("--") && *arg != "--") {
+        if let Some(to) = flag.strip_prefix("--stdin=") {
+            streams[0] = Stdio::parse(to);
+        } else if let Some(to) = flag.strip_prefix("--stdout=") {
+            streams[1] = Stdio::parse(to);
+        } else if let Some(to) = flag.strip_prefix("--stderr=") {
+            streams[2] = Stdio::parse(to);
+        } else if let Some(stop) = flag.strip_prefix("--stop-at=") {
+            stop_at = StopAt::parse(stop)?;
+        } else if flag == "--tty" {
+            streams = [Stdio::Pty, Stdio::Pty, Stdio::Pty];
+        } else {
+            return Err(Error::Parse(format!("Unknown option: {}", flag)));
+        }
+    }
+    let program_path = args.next().ok_or_else(|| Error::Parse("No program to launch".to_string()))?;
+    // rdb ./prog -- arg1 arg2, the -- is optional
+    let program_args: Vec<&String> = args.skip_while(|arg| *arg == "--").collect();
+    let [stdin, stdout, stderr] = streams;
+    let options = LaunchOptions::new(program_path)
+        .args(program_args)
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(stderr)
+        .stop_at(stop_at);
+    Process::launch_with_options(&options)
+}
+
+/// Attaches to the process called `name`, either one running now or, with `wait`, the first one
+/// to start from here on. `choose` picks between several matches and may cancel with `None`.
+pub fn attach_by_name(name: &str, wait: bool, choose: impl FnOnce(&[ProcessEntry]) -> Option<Pid>) -> Result<Process> {
+    let candidates = if wait {
+        wait_for_processes(name, WAIT_INTERVAL)
+    } else {
+        find_processes(name)
+    };
+    let pid = match candidates.as_slice() {
+        [] => return Err(Error::ProcessNotFound(name.to_string())),
+        [only] => only.pid,
+        several => choose(several).ok_or_else(|| Error::Parse("No process chosen".to_string()))?,
+    };
+    Process::attach(&pid.to_string())
+}
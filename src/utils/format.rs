@@ -0,0 +1,118 @@
+use crate::error::{Error, Result};
+
+/// How `x/` shows each unit of memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayFormat {
+    Hex,
+    Decimal,
+    Char,
+    String,
+    Float,
+}
+
+/// A parsed gdb style `x/` spec: how many units, how wide each is and how to show them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExamineFormat {
+    pub count: usize,
+    pub unit_size: usize,
+    pub format: DisplayFormat,
+}
+
+impl ExamineFormat {
+    /// Parses the part after `x/`, e.g. `8gx`, `4c` or `s`. Everything is optional and defaults
+    /// to one hex word, `c` defaults to bytes and `f` to giant words.
+    pub fn parse(text: &str) -> Result<Self> {
+        let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let count = if digits == 0 {
+            1
+        } else {
+            text[..digits].parse::<usize>().map_err(|_| Error::Parse(format!("Invalid count: {}", text)))?
+        };
+        let mut unit_size = None;
+        let mut format = None;
+        for letter in text[digits..].chars() {
+            match letter {
+                'b' => unit_size = Some(1),
+                'h' => unit_size = Some(2),
+                'w' => unit_size = Some(4),
+                'g' => unit_size = Some(8),
+                'x' => format = Some(DisplayFormat::Hex),
+                'd' => format = Some(DisplayFormat::Decimal),
+                'c' => format = Some(DisplayFormat::Char),
+                's' => format = Some(DisplayFormat::String),
+                'f' => format = Some(DisplayFormat::Float),
+                _ => return Err(Error::Parse(format!(
+                    "Invalid format letter '{}', expected a unit (b, h, w, g) or a format (x, d, c, s, f)", letter
+                ))),
+            }
+        }
+        let format = format.unwrap_or(DisplayFormat::Hex);
+        let unit_size = match (format, unit_size) {
+            (DisplayFormat::Char, None) | (DisplayFormat::String, _) => 1,
+            (DisplayFormat::Float, None) => 8,
+            (DisplayFormat::Float, Some(size)) if size < 4 => {
+                return Err(Error::Parse("Floats are either w (float) or g (double) sized".to_string()));
+            }
+            (_, size) => size.unwrap_or(4),
+        };
+        if count == 0 {
+            return Err(Error::Parse("Count must be at least 1".to_string()));
+        }
+        Ok(Self { count, unit_size, format })
+    }
+
+    /// Formats `bytes`, which start at `address`, as rows of units. Strings are not handled here
+    /// since their length is only known once the terminating NUL has been read.
+    pub fn format_units(&self, address: u64, bytes: &[u8]) -> Vec<String> {
+        let per_row = match (self.format, self.unit_size) {
+            (DisplayFormat::Char, _) => 8,
+            (_, size) => (16 / size).min(8),
+        };
+        bytes
+            .chunks(self.unit_size * per_row)
+            .enumerate()
+            .map(|(row, chunk)| {
+                let units: Vec<String> = chunk.chunks_exact(self.unit_size).map(|unit| self.format_unit(unit)).collect();
+                format!("{:#018x}:\t{}", address + (row * self.unit_size * per_row) as u64, units.join("\t"))
+            })
+            .collect()
+    }
+
+    fn format_unit(&self, unit: &[u8]) -> String {
+        let mut raw = [0u8; 8];
+        raw[..unit.len()].copy_from_slice(unit);
+        let value = u64::from_le_bytes(raw);
+        match (self.format, self.unit_size) {
+            (DisplayFormat::Hex, size) => format!("{:#0width$x}", value, width = 2 + 2 * size),
+            (DisplayFormat::Decimal, 1) => (value as i8).to_string(),
+            (DisplayFormat::Decimal, 2) => (value as i16).to_string(),
+            (DisplayFormat::Decimal, 4) => (value as i32).to_string(),
+            (DisplayFormat::Decimal, _) => (value as i64).to_string(),
+            (DisplayFormat::Char, _) => format!("{} '{}'", value as u8 as i8, std::ascii::escape_default(unit[0])),
+            (DisplayFormat::Float, 4) => f32::from_bits(value as u32).to_string(),
+            (DisplayFormat::Float, _) => f64::from_bits(value).to_string(),
+            (DisplayFormat::String, _) => format_string(unit),
+        }
+    }
+}
+
+/// Quotes and escapes a string read out of the inferior, without its NUL.
+pub fn format_string(bytes: &[u8]) -> String {
+    format!("{:?}", String::from_utf8_lossy(bytes))
+}
+
+/// Classic 16 bytes per row hexdump with an ASCII gutter, unprintable bytes show as `.`.
+pub fn hexdump(address: u64, bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            format!("{:#018x}:  {:<47}  |{}|", address + row as u64 * 16, hex.join(" "), ascii)
+        })
+        .collect()
+}
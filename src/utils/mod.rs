@@ -1 +1,4 @@
-pub mod attach;
\ No newline at end of file
+pub mod attach;
+pub mod format;
+pub mod parse;
+pub mod proc;
\ No newline at end of file
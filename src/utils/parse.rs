@@ -0,0 +1,37 @@
+use crate::error::{Error, Result};
+use crate::rdb::address::VirtAddr;
+
+/// Parses an unsigned integer written either as hex (`0x1f`) or decimal (`31`).
+pub fn parse_u64(text: &str) -> Result<u64> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse::<u64>()
+    };
+    parsed.map_err(|_| Error::Parse(format!("Invalid integer: {}", text)))
+}
+
+/// An address in the inferior, written like [`parse_u64`] takes it.
+pub fn parse_address(text: &str) -> Result<VirtAddr> {
+    parse_u64(text).map(VirtAddr::new)
+}
+
+pub fn parse_f64(text: &str) -> Result<f64> {
+    text.parse::<f64>().map_err(|_| Error::Parse(format!("Invalid floating point number: {}", text)))
+}
+
+/// Parses a byte vector in the same format `register read` prints it: `[0x01,0x02,...]`.
+pub fn parse_vector(text: &str) -> Result<Vec<u8>> {
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .ok_or_else(|| Error::Parse(format!("Invalid vector, expected [0x01,0x02,...]: {}", text)))?;
+    inner
+        .split(',')
+        .map(|byte| {
+            let byte = byte.trim();
+            let hex = byte.strip_prefix("0x")
+                .ok_or_else(|| Error::Parse(format!("Invalid byte, expected 0x prefixed hex: {}", byte)))?;
+            u8::from_str_radix(hex, 16).map_err(|_| Error::Parse(format!("Invalid byte: {}", byte)))
+        })
+        .collect()
+}
@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+use std::time::Duration;
+use nix::unistd::{getpid, Pid};
+
+/// A process found by scanning `/proc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessEntry {
+    pub pid: Pid,
+    /// `/proc/pid/comm`, the kernel cuts it to 15 bytes.
+    pub name: String,
+    /// Arguments joined with spaces, empty for kernel threads and zombies.
+    pub cmdline: String,
+}
+
+/// Every process whose `comm` or the file name of whose `argv[0]` is `name`, sorted by pid. We
+/// are never in the list, and neither is anything that exits while we look.
+pub fn find_processes(name: &str) -> Vec<ProcessEntry> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut processes: Vec<ProcessEntry> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+        .map(Pid::from_raw)
+        .filter(|pid| *pid != getpid())
+        .filter_map(read_entry)
+        .filter(|entry| matches_name(entry, name))
+        .collect();
+    processes.sort_by_key(|entry| entry.pid.as_raw());
+    processes
+}
+
+/// Polls every `interval` until processes named `name` show up that were not running when we
+/// started waiting, like a daemon its supervisor is about to (re)start.
+pub fn wait_for_processes(name: &str, interval: Duration) -> Vec<ProcessEntry> {
+    let existing: HashSet<Pid> = find_processes(name).iter().map(|entry| entry.pid).collect();
+    loop {
+        let new: Vec<ProcessEntry> = find_processes(name)
+            .into_iter()
+            .filter(|entry| !existing.contains(&entry.pid))
+            .collect();
+        if !new.is_empty() {
+            return new;
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+fn read_entry(pid: Pid) -> Option<ProcessEntry> {
+    let name = std::fs::read_to_string(format!("/proc/{}/comm", pid.as_raw())).ok()?;
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid.as_raw())).unwrap_or_default();
+    let cmdline = cmdline
+        .split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(ProcessEntry { pid, name: name.trim_end_matches('\n').to_string(), cmdline })
+}
+
+fn matches_name(entry: &ProcessEntry, name: &str) -> bool {
+    // comm alone misses names longer than 15 bytes, argv[0] alone misses processes that rewrote it
+    let program = entry.cmdline.split(' ').next().unwrap_or("");
+    entry.name == name || program.rsplit('/').next() == Some(name)
+}
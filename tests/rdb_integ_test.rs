@@ -1,23 +1,45 @@
 use std::ffi::CString;
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
 use std::path::Path;
-use std::process;
 use std::time::Duration;
+use nix::errno::Errno;
 use nix::fcntl::{fcntl, FcntlArg, FdFlag};
-use nix::libc::pid_t;
+use nix::libc;
 use nix::sys::ptrace;
-use nix::sys::wait::waitpid;
+use nix::sys::resource::{Resource, RLIM_INFINITY};
+use nix::sys::signal::{kill, Signal};
 use nix::unistd::{close, execvp, fork, pipe, read, write, ForkResult, Pid};
-use rdb::rdb::process::{Process, ProcessState};
+use rdb::rdb::address::VirtAddr;
+use rdb::rdb::attach_diagnosis::AttachDenial;
+use rdb::rdb::debug_file::find_debug_file;
+use rdb::rdb::disassembler::{disassemble, Syntax};
+use rdb::rdb::elf::Elf;
+use rdb::rdb::launch_options::{LaunchOptions, Stdio, StopAt};
+use rdb::rdb::process::{FollowForkMode, HardwareStoppoint, Process, ProcessState};
+use rdb::rdb::stop_reason::{StopInfo, TrapKind};
+use rdb::rdb::stoppoint_collection::Stoppoint;
+use rdb::rdb::register_info::RegisterId;
+use rdb::rdb::watchpoint::StoppointMode;
+use rdb::utils::attach::attach_by_name;
+use rdb::utils::proc::{find_processes, wait_for_processes};
+use rdb::Error;
 
 #[test]
 fn test_process_launch_success(){
-    let proc = Process::launch("yes")
+    let proc = launch_yes()
         .expect("Failed to launch process");
 
     assert!(process_running(proc.pid()));
     drop(proc);
 }
 
+/// `yes` with its output thrown away, it gets to run for a moment between detach and kill.
+fn launch_yes() -> Result<Process, Error> {
+    Process::launch_with_options(&LaunchOptions::new("yes").stdout(Stdio::Null))
+}
+
 fn process_running(pid: Pid) -> bool {
     // proc is a special directory on linux virtual Filesystem
     // /proc/123
@@ -46,11 +68,27 @@ fn process_running(pid: Pid) -> bool {
 #[test]
 fn test_process_launch_nonexistent_process(){
     let proc = Process::launch("/random/non/existent/path/hopefully");
-    if let Err(_) = proc {
-        assert!(true)
-    }else{
-        assert!(false)
-    }
+    assert!(proc.is_err())
+}
+
+#[test]
+fn launch_and_attach_report_structured_errors(){
+    let launched = Process::launch("/random/non/existent/path/hopefully");
+    assert!(matches!(launched, Err(Error::Launch { errno: Errno::ENOENT })));
+
+    assert!(matches!(Process::attach("abc"), Err(Error::InvalidPid(_))));
+    assert!(matches!(Process::attach("-5"), Err(Error::InvalidPid(_))));
+}
+
+#[test]
+fn wait_on_reaped_process_is_an_error_not_an_exit(){
+    let mut proc = Process::launch("true").expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Exited);
+
+    assert_eq!(proc.wait_on_signal().unwrap_err(), Error::NoSuchProcess(proc.pid().as_raw()));
 }
 
 
@@ -90,6 +128,10 @@ fn launch_test_process(program_path: &str) -> Result<Process, String> {
             Ok(ForkResult::Child) => {
                 close(read_fd).ok(); // we only want to write from the child
 
+                // keep the output of `yes` out of the test log
+                let null = std::fs::File::create("/dev/null").expect("Failed to open /dev/null");
+                libc::dup2(null.as_raw_fd(), 1);
+
                 let program_path_c = CString::new(program_path)
                     .expect("Cstring conversion failed");
                 let exec_args = vec![program_path_c.clone()];
@@ -98,13 +140,11 @@ fn launch_test_process(program_path: &str) -> Result<Process, String> {
                 // if the exec in the above line works fine then we never write something to the pipe
                 // nor do we ever close it
 
-                if let Err(e) = exec_res {
-                    let _ = write(&write_fd, format!("Tracing child process failed: {}", e).as_bytes());
-                    eprintln!("Exec failed: {}", e);
-                    close(write_fd).ok();
-                    return Err(format!("Exec Failed!: {}", e))
-                }
-                unreachable!();
+                let Err(e) = exec_res;
+                let _ = write(&write_fd, format!("Tracing child process failed: {}", e).as_bytes());
+                eprintln!("Exec failed: {}", e);
+                close(write_fd).ok();
+                Err(format!("Exec Failed!: {}", e))
             }
             Err(e) => {
                 Err(format!("Fork failed: {}", e))
@@ -121,8 +161,8 @@ fn test_process_attach_success(){
         Ok(proc) => {
             let pid_arg = proc.pid().as_raw().to_string();
             let attach_res = Process::attach(&pid_arg);
-            if let Err(_) = attach_res {
-                assert!(false, "attach_failed!")
+            if attach_res.is_err() {
+                panic!("attach_failed!")
             }
             std::thread::sleep(Duration::from_millis(50));
             let process_state: Result<char, String> = get_process_state(proc.pid().as_raw() as u32);
@@ -131,12 +171,12 @@ fn test_process_attach_success(){
                     assert_eq!(c,'t')
                 }
                 Err(s) => {
-                    assert!(false, "{}", s)
+                    panic!("{}", s)
                 }
             }
         }
         Err(s) => {
-            assert!(false, "{}", s);
+            panic!("{}", s);
         }
     }
 }
@@ -174,12 +214,1092 @@ fn get_process_state(pid: u32) -> Result<char, String> {
 
 #[test]
 fn test_process_attach_pid_0_fails(){
-    match Process::attach("0"){
-        Ok(_) => {
-            assert!(false, "attached to process with pid 0")
-        }
-        Err(_) => {
-            assert!(true)
-        }
+    if Process::attach("0").is_ok() {
+        panic!("attached to process with pid 0")
+    }
+}
+#[test]
+fn test_write_register_reaches_inferior(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+
+    proc.registers_mut().write_by_id(RegisterId::R13, 0xcafecafe_u64)
+        .expect("Failed to write r13");
+    proc.registers_mut().write_by_id(RegisterId::Ah, 0x42_u8)
+        .expect("Failed to write ah");
+
+    let regs = ptrace::getregs(proc.pid()).expect("getregs failed");
+    assert_eq!(regs.r13, 0xcafecafe);
+    assert_eq!((regs.rax >> 8) & 0xff, 0x42);
+}
+
+#[test]
+fn test_read_register_after_write(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+
+    proc.registers_mut().write_by_id(RegisterId::Xmm3, 0x1122334455667788_u64.to_le_bytes())
+        .expect("Failed to write xmm3");
+    proc.registers_mut().write_by_id(RegisterId::R8d, 0xdeadbeef_u32)
+        .expect("Failed to write r8d");
+    proc.registers_mut().read_all().expect("Failed to re-read registers");
+
+    let xmm3: [u8; 16] = proc.registers().read_by_id_as(RegisterId::Xmm3);
+    assert_eq!(xmm3[..8], 0x1122334455667788_u64.to_le_bytes());
+    assert_eq!(xmm3[8..], [0; 8]);
+    assert_eq!(proc.registers().read_by_id_as::<u32>(RegisterId::R8d), 0xdeadbeef);
+    assert!(proc.registers().read_by_id_as::<u64>(RegisterId::Rip) != 0);
+}
+
+#[test]
+fn test_write_register_too_wide_fails(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+
+    assert!(proc.registers_mut().write_by_id(RegisterId::Ax, 0x1ffff_u32).is_err());
+}
+
+fn entry_point(pid: Pid) -> VirtAddr {
+    // /proc/pid/auxv is a list of (type, value) pairs, AT_ENTRY (9) is where the program starts
+    let auxv = std::fs::read(format!("/proc/{}/auxv", pid.as_raw())).expect("Failed to read auxv");
+    auxv.chunks_exact(16)
+        .map(|pair| (
+            u64::from_le_bytes(pair[..8].try_into().unwrap()),
+            u64::from_le_bytes(pair[8..].try_into().unwrap())
+        ))
+        .find(|(key, _)| *key == 9)
+        .map(|(_, value)| VirtAddr::new(value))
+        .expect("No AT_ENTRY in auxv")
+}
+
+#[test]
+fn test_breakpoint_site_patches_and_restores_memory(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let entry = entry_point(proc.pid());
+    let original = ptrace::read(proc.pid(), entry.addr() as ptrace::AddressType).unwrap();
+
+    let id = proc.create_breakpoint_site(entry, false).expect("Failed to create breakpoint site");
+    assert!(proc.create_breakpoint_site(entry, false).is_err());
+    proc.enable_breakpoint_site(id).expect("Failed to enable breakpoint site");
+    let patched = ptrace::read(proc.pid(), entry.addr() as ptrace::AddressType).unwrap();
+    assert_eq!(patched & 0xff, 0xcc);
+    assert_eq!(patched & !0xff, original & !0xff);
+
+    proc.remove_breakpoint_site(id).expect("Failed to remove breakpoint site");
+    assert_eq!(ptrace::read(proc.pid(), entry.addr() as ptrace::AddressType).unwrap(), original);
+    assert!(proc.breakpoint_sites().is_empty());
+}
+
+#[test]
+fn test_breakpoint_site_hit_and_stepped_over(){
+    let mut proc = Process::launch("true")
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let entry = entry_point(proc.pid());
+    let id = proc.create_breakpoint_site(entry, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Stopped);
+    assert_eq!(reason.info, StopInfo::Trap(TrapKind::SoftwareBreakpoint(id)));
+    assert_eq!(proc.get_pc(), entry);
+
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Exited);
+    assert_eq!(reason.info, StopInfo::ExitCode(0));
+}
+
+fn compile_target(name: &str) -> String {
+    compile_target_with(name, &["-O0"])
+}
+
+fn compile_target_with(name: &str, flags: &[&str]) -> String {
+    // targets are built on demand so no binaries have to be checked in
+    let source = format!("{}/tests/targets/{}.c", env!("CARGO_MANIFEST_DIR"), name);
+    let output = format!("{}/{}", env!("CARGO_TARGET_TMPDIR"), name);
+    let status = std::process::Command::new("cc")
+        .arg("-g")
+        .args(flags)
+        .args(["-pthread", "-o", &output, &source])
+        .status()
+        .expect("Failed to run cc");
+    assert!(status.success(), "Failed to compile {}", source);
+    output
+}
+
+#[test]
+fn test_hardware_breakpoint_hit_without_patching_memory(){
+    let mut proc = Process::launch("true")
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let entry = entry_point(proc.pid());
+    let original = ptrace::read(proc.pid(), entry.addr() as ptrace::AddressType).unwrap();
+
+    let id = proc.create_breakpoint_site(entry, true).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+    assert_eq!(ptrace::read(proc.pid(), entry.addr() as ptrace::AddressType).unwrap(), original);
+
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::Trap(TrapKind::HardwareBreakpoint(id)));
+    assert_eq!(proc.get_pc(), entry);
+    assert_eq!(proc.get_current_hardware_stoppoint(), Some(HardwareStoppoint::BreakpointSite(id)));
+
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Exited);
+    assert_eq!(reason.info, StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_watchpoint_reports_old_and_new_value(){
+    let target = compile_target("hardware_watch");
+    let mut proc = Process::launch(&target)
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::Trap(TrapKind::Unknown));
+    let address = VirtAddr::new(proc.registers().read_by_id_as::<u64>(RegisterId::R12));
+
+    assert!(proc.create_watchpoint(address + 1, StoppointMode::Write, 8).is_err());
+    assert!(proc.create_watchpoint(address, StoppointMode::Write, 3).is_err());
+    let id = proc.create_watchpoint(address, StoppointMode::Write, 8).unwrap();
+    proc.enable_watchpoint(id).unwrap();
+
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::Trap(TrapKind::Watchpoint { id, old_value: 0, new_value: 42 }));
+    assert_eq!(proc.get_current_hardware_stoppoint(), Some(HardwareStoppoint::Watchpoint(id)));
+    let watchpoint = proc.watchpoints().get_by_id(id).unwrap();
+    assert_eq!((watchpoint.previous_data(), watchpoint.data()), (0, 42));
+
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    let watchpoint = proc.watchpoints().get_by_id(id).unwrap();
+    assert_eq!((watchpoint.previous_data(), watchpoint.data()), (42, 43));
+
+    proc.remove_watchpoint(id).unwrap();
+    // what thread list shows does not need the watchpoint any more
+    let stopped = StopInfo::Trap(TrapKind::Watchpoint { id, old_value: 42, new_value: 43 });
+    assert_eq!(proc.current_thread().stop_reason().map(|r| r.info), Some(stopped));
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Exited);
+    assert_eq!(reason.info, StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_hardware_stoppoints_limited_to_four(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let entry = entry_point(proc.pid());
+
+    for offset in 0..4 {
+        let id = proc.create_breakpoint_site(entry + offset, true).unwrap();
+        proc.enable_breakpoint_site(id).expect("Failed to enable hardware breakpoint");
     }
-}
\ No newline at end of file
+    let id = proc.create_breakpoint_site(entry + 4, true).unwrap();
+    assert!(proc.enable_breakpoint_site(id).is_err());
+
+    proc.remove_breakpoint_site(1).unwrap();
+    proc.enable_breakpoint_site(id).expect("Freed debug register was not reused");
+}
+
+#[test]
+fn test_step_instruction_over_breakpoint_site(){
+    let mut proc = Process::launch("true")
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let entry = entry_point(proc.pid());
+    let id = proc.create_breakpoint_site(entry, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(proc.get_pc(), entry);
+
+    let reason = proc.step_instruction().expect("Failed to step");
+    assert_eq!(reason.info, StopInfo::Trap(TrapKind::SingleStep));
+    assert_ne!(proc.get_pc(), entry);
+    // the site has to be back in place once the step is done
+    assert!(proc.breakpoint_sites().get_by_id(id).unwrap().is_enabled());
+    let word = ptrace::read(proc.pid(), entry.addr() as ptrace::AddressType).unwrap();
+    assert_eq!(word & 0xff, 0xcc);
+}
+
+#[test]
+fn test_step_instruction_attached(){
+    let target = launch_test_process("yes").expect("Failed to launch process");
+    let mut proc = Process::attach(&target.pid().as_raw().to_string())
+        .expect("Failed to attach");
+    proc.wait_on_signal().expect("waitpid failed");
+
+    let reason = proc.step_instruction().expect("Failed to step");
+    assert_eq!(reason.info, StopInfo::Trap(TrapKind::SingleStep));
+}
+
+#[test]
+fn test_stop_reason_for_terminated_process(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Stopped);
+    assert_eq!(reason.info, StopInfo::Signal(Signal::SIGTRAP));
+
+    proc.resume().expect("resume failed");
+    kill(proc.pid(), Signal::SIGKILL).expect("Failed to kill");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Terminated);
+    assert_eq!(reason.info, StopInfo::Signal(Signal::SIGKILL));
+}
+
+/// Launches the memory target and returns it stopped at its int3 with the buffer address.
+fn launch_memory_target() -> (Process, VirtAddr) {
+    let target = compile_target("memory");
+    let mut proc = Process::launch(&target)
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    let buffer = VirtAddr::new(proc.registers().read_by_id_as::<u64>(RegisterId::R12));
+    (proc, buffer)
+}
+
+#[test]
+fn test_read_memory_across_pages(){
+    let (proc, buffer) = launch_memory_target();
+
+    let memory = proc.read_memory(buffer + 0x1000 - 32, 64).expect("read_memory failed");
+    let expected: Vec<u8> = (0x1000 - 32..0x1000 + 32).map(|i: u64| i as u8).collect();
+    assert_eq!(memory, expected);
+    assert!(proc.read_memory(buffer, 0).unwrap().is_empty());
+}
+
+#[test]
+fn test_read_memory_reports_first_unreadable_address(){
+    let (proc, buffer) = launch_memory_target();
+
+    let result = proc.read_memory(buffer + 0x2000 - 5, 16);
+    assert_eq!(result, Err(Error::MemoryAccess { addr: buffer + 0x2000 }));
+    let result = proc.read_memory(buffer + 0x2000 + 3, 4);
+    assert_eq!(result, Err(Error::MemoryAccess { addr: buffer + 0x2000 + 3 }));
+}
+
+#[test]
+fn test_write_memory_unaligned_across_pages(){
+    let (mut proc, buffer) = launch_memory_target();
+
+    let data: Vec<u8> = (0..13).map(|i| 0xa0 + i).collect();
+    proc.write_memory(buffer + 0x1000 - 6, &data).expect("write_memory failed");
+    let memory = proc.read_memory(buffer + 0x1000 - 7, 15).unwrap();
+    assert_eq!(memory[0], 0xf9);
+    assert_eq!(&memory[1..14], &data[..]);
+    assert_eq!(memory[14], 0x07);
+
+    let result = proc.write_memory(buffer + 0x2000 - 2, &[1, 2, 3, 4]);
+    assert_eq!(result, Err(Error::MemoryAccess { addr: buffer + 0x2000 }));
+}
+
+#[test]
+fn test_failed_write_memory_keeps_saved_breakpoint_data(){
+    let target = compile_target("memory");
+    let mut proc = Process::launch_with_options(&LaunchOptions::new(target).args(["unmap"]))
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    let buffer = VirtAddr::new(proc.registers().read_by_id_as::<u64>(RegisterId::R12));
+    let id = proc.create_breakpoint_site(buffer + 0x1000 + 1, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+    assert_eq!(proc.breakpoint_sites().get_by_id(id).unwrap().saved_data(), 0x01);
+
+    // the target unmaps the page under the site, the write stops short of it
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    let result = proc.write_memory(buffer + 0x1000 - 2, &[1, 2, 3, 4]);
+    assert_eq!(result, Err(Error::MemoryAccess { addr: buffer + 0x1000 }));
+    assert_eq!(proc.read_memory(buffer + 0x1000 - 2, 2).unwrap(), vec![1, 2]);
+    assert_eq!(proc.breakpoint_sites().get_by_id(id).unwrap().saved_data(), 0x01);
+}
+
+#[test]
+fn test_memory_hides_and_keeps_breakpoints(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let entry = entry_point(proc.pid());
+    let original = proc.read_memory(entry, 4).unwrap();
+
+    let id = proc.create_breakpoint_site(entry + 1, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+    assert_eq!(proc.read_memory(entry, 4).unwrap()[1], 0xcc);
+    assert_eq!(proc.read_memory_without_traps(entry, 4).unwrap(), original);
+
+    // writing over an armed site keeps the int3 and restores the new byte on disable
+    proc.write_memory(entry, &[0x90, 0x90]).unwrap();
+    assert_eq!(proc.read_memory(entry, 2).unwrap(), vec![0x90, 0xcc]);
+    assert_eq!(proc.read_memory_without_traps(entry, 2).unwrap(), vec![0x90, 0x90]);
+    proc.disable_breakpoint_site(id).unwrap();
+    assert_eq!(proc.read_memory(entry, 2).unwrap(), vec![0x90, 0x90]);
+}
+
+#[test]
+fn test_disassemble_hides_breakpoints(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let pc = proc.get_pc();
+    let before = disassemble(&proc, pc, 3, Syntax::Att).expect("disassemble failed");
+    assert_eq!(before.len(), 3);
+    assert_eq!(before[0].address, pc);
+
+    let id = proc.create_breakpoint_site(before[1].address, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+    assert_eq!(disassemble(&proc, pc, 3, Syntax::Att).unwrap(), before);
+}
+
+#[test]
+fn test_launch_options_reach_inferior(){
+    let options = LaunchOptions::new("yes")
+        .args(["one", "two words"])
+        .env_clear()
+        .env("RDB_TEST", "value")
+        .env("RDB_REMOVED", "gone")
+        .env_remove("RDB_REMOVED")
+        .current_dir("/tmp");
+    let mut proc = Process::launch_with_options(&options)
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let pid = proc.pid().as_raw();
+
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).unwrap();
+    assert_eq!(cmdline, b"yes\0one\0two words\0");
+    let environ = std::fs::read(format!("/proc/{}/environ", pid)).unwrap();
+    assert_eq!(environ, b"RDB_TEST=value\0");
+    let cwd = std::fs::read_link(format!("/proc/{}/cwd", pid)).unwrap();
+    assert_eq!(cwd, Path::new("/tmp"));
+    assert_eq!(proc.launch_options(), Some(&options));
+}
+
+#[test]
+fn test_launch_relative_program_with_current_dir(){
+    let target = compile_target("hardware_watch");
+    let relative = Path::new(&target).strip_prefix(std::env::current_dir().unwrap())
+        .expect("target dir is inside the package");
+    let options = LaunchOptions::new(relative.to_str().unwrap()).current_dir("/");
+    let mut proc = Process::launch_with_options(&options)
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+
+    let result = Process::launch_with_options(&LaunchOptions::new("yes").current_dir("/nonexistent"));
+    assert!(matches!(result, Err(Error::Launch { errno: Errno::ENOENT })));
+}
+
+#[test]
+fn test_pty_captures_output(){
+    let options = LaunchOptions::new("sh")
+        .args(["-c", "tty; echo out; echo err >&2"])
+        .stdin(Stdio::Pty)
+        .stdout(Stdio::Pty)
+        .stderr(Stdio::Pty);
+    let mut proc = Process::launch_with_options(&options)
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let mut terminal = proc.terminal().expect("no pty").try_clone().unwrap();
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        // EIO marks the end once sh is gone
+        let _ = terminal.read_to_string(&mut output);
+        output
+    });
+    proc.resume().expect("resume failed");
+    // sh stops with SIGCHLD when tty exits
+    while proc.wait_on_signal().expect("waitpid failed").state == ProcessState::Stopped {
+        proc.resume().expect("resume failed");
+    }
+    let output = reader.join().unwrap();
+    let lines: Vec<&str> = output.lines().map(|line| line.trim_end_matches('\r')).collect();
+    assert!(lines[0].starts_with("/dev/pts/"), "not a tty: {}", lines[0]);
+    assert_eq!(&lines[1..], ["out", "err"]);
+}
+
+#[test]
+fn test_redirect_to_files(){
+    let input = format!("{}/redirect_input.txt", env!("CARGO_TARGET_TMPDIR"));
+    let output = format!("{}/redirect_output.txt", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&input, "line one\nline two\n").unwrap();
+    std::fs::write(&output, "stale contents that are longer than the new ones").unwrap();
+    let options = LaunchOptions::new("cat")
+        .stdin(Stdio::File(input.into()))
+        .stdout(Stdio::File(output.clone().into()))
+        .stderr(Stdio::Null);
+    let mut proc = Process::launch_with_options(&options)
+        .expect("Failed to launch process");
+    assert!(proc.terminal().is_none());
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::ExitCode(0));
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "line one\nline two\n");
+
+    let missing = LaunchOptions::new("cat").stdin(Stdio::File("/nonexistent/input".into()));
+    let result = Process::launch_with_options(&missing);
+    assert!(matches!(result, Err(Error::Redirect { errno: Errno::ENOENT, .. })));
+}
+
+#[test]
+fn test_launch_stops_at_entry_point(){
+    let options = LaunchOptions::new("true").stop_at(StopAt::Entry);
+    let mut proc = Process::launch_with_options(&options)
+        .expect("Failed to launch process");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Stopped);
+    assert_eq!(reason.info, StopInfo::Signal(Signal::SIGTRAP));
+    let entry = entry_point(proc.pid());
+    assert_eq!(proc.get_pc(), entry);
+    // the internal breakpoint is gone, and no user id was spent on it
+    assert!(proc.breakpoint_sites().is_empty());
+    assert_ne!(proc.read_memory(entry, 1).unwrap(), [0xcc]);
+    assert_eq!(proc.create_breakpoint_site(entry, false).unwrap(), 1);
+}
+
+#[test]
+fn test_launch_stops_at_main(){
+    let target = compile_target("stop_at");
+    let options = LaunchOptions::new(target).stop_at(StopAt::Main);
+    let mut proc = Process::launch_with_options(&options)
+        .expect("Failed to launch process");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::Signal(Signal::SIGTRAP));
+    let main = proc.get_pc();
+    assert_ne!(main, entry_point(proc.pid()));
+
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(VirtAddr::new(proc.registers().read_by_id_as::<u64>(RegisterId::R12)), main);
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_symbols_account_for_load_bias(){
+    let target = compile_target("stop_at");
+    let mut proc = Process::launch(&target).expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let main = proc.lookup_symbol("main").expect("no main");
+    assert!(matches!(proc.lookup_symbol("no_such_symbol"), Err(Error::NoSuchSymbol(_))));
+
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    // the target's own idea of where main is, PIE load bias included
+    assert_eq!(VirtAddr::new(proc.registers().read_by_id_as::<u64>(RegisterId::R12)), main);
+    let location = proc.symbolize(main).expect("main not symbolized");
+    assert_eq!((location.symbol.name.as_str(), location.offset), ("main", 0));
+    let location = proc.symbolize(proc.get_pc()).expect("pc not symbolized");
+    assert_eq!(location.symbol.name, "main");
+    assert_eq!(location.to_string(), format!("main+{:#x}", proc.get_pc() - main));
+    let instructions = disassemble(&proc, main, 8, Syntax::Att).expect("disassemble failed");
+    assert!(instructions.iter().any(|i| i.text == format!("lea {:#x} <main>,%r12", main)));
+}
+
+#[test]
+fn test_signals_reach_the_program_after_continue(){
+    let target = compile_target("signal");
+    let mut proc = Process::launch(&target).expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::Signal(Signal::SIGUSR1));
+    proc.resume().expect("resume failed");
+    // the handler ran once, the exit status is its count
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::ExitCode(1));
+
+    let mut proc = Process::launch_with_options(&LaunchOptions::new(&target).args(["segv"]))
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    // stepping delivers the signal too, the step ends in the handler
+    let handler = proc.lookup_symbol("on_usr1").unwrap();
+    let reason = proc.step_instruction().expect("step failed");
+    assert_eq!(reason.info, StopInfo::Trap(TrapKind::SingleStep));
+    assert_eq!(proc.get_pc(), handler);
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::Signal(Signal::SIGSEGV));
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!((reason.state, reason.info), (ProcessState::Terminated, StopInfo::Signal(Signal::SIGSEGV)));
+}
+
+#[test]
+fn test_launch_disables_randomization(){
+    let mut proc = Process::launch_with_options(&LaunchOptions::new("true"))
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    assert!(proc.is_randomization_disabled().unwrap());
+    assert_eq!(proc.randomization_error(), None);
+
+    let options = LaunchOptions::new("true").disable_randomization(false);
+    let mut proc = Process::launch_with_options(&options)
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    assert!(!proc.is_randomization_disabled().unwrap());
+    // not asked for, so nothing went wrong
+    assert_eq!(proc.randomization_error(), None);
+}
+
+#[test]
+fn test_launch_sets_rlimits(){
+    let options = LaunchOptions::new("true")
+        .rlimit(Resource::RLIMIT_CORE, 0, 0)
+        .rlimit(Resource::RLIMIT_STACK, 1 << 20, RLIM_INFINITY);
+    let mut proc = Process::launch_with_options(&options)
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let limits = std::fs::read_to_string(format!("/proc/{}/limits", proc.pid())).unwrap();
+    let limit = |name: &str| -> Vec<String> {
+        let line = limits.lines().find(|line| line.starts_with(name)).unwrap();
+        line[name.len()..].split_whitespace().take(2).map(String::from).collect()
+    };
+    assert_eq!(limit("Max core file size"), ["0", "0"]);
+    assert_eq!(limit("Max stack size"), ["1048576", "unlimited"]);
+
+    // a soft limit above the hard one is refused in the child and reported as a launch failure
+    let invalid = LaunchOptions::new("true").rlimit(Resource::RLIMIT_CORE, 2, 1);
+    let result = Process::launch_with_options(&invalid);
+    assert!(matches!(result, Err(Error::Launch { errno: Errno::EINVAL })));
+}
+
+fn tracer_pid(pid: Pid) -> i32 {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).unwrap();
+    status.lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))
+        .and_then(|tracer| tracer.trim().parse().ok())
+        .unwrap()
+}
+
+#[test]
+fn test_detach_leaves_launched_process_running(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let pid = proc.pid();
+    let entry = entry_point(pid);
+    // sitting at the exec trap, the first instruction run after detaching is the one under the site
+    let id = proc.create_breakpoint_site(entry, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+
+    proc.detach().expect("detach failed");
+    assert_eq!(proc.process_state, ProcessState::Detached);
+    assert!(matches!(proc.detach(), Err(Error::InvalidState { state: ProcessState::Detached, .. })));
+    assert!(matches!(proc.resume(), Err(Error::InvalidState { .. })));
+    // dropping a detached process must not kill it
+    drop(proc);
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(tracer_pid(pid), 0);
+    assert_ne!(get_process_state(pid.as_raw() as u32).unwrap(), 'T');
+    // yes would have died of SIGTRAP had the int3 been left in place
+    kill(pid, Signal::SIGKILL).unwrap();
+    let status = nix::sys::wait::waitpid(pid, None).unwrap();
+    assert_eq!(status, nix::sys::wait::WaitStatus::Signaled(pid, Signal::SIGKILL, false));
+}
+
+#[test]
+fn test_detach_leaves_stopped_process_stopped(){
+    let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+    let pid = Pid::from_raw(child.id() as i32);
+    kill(pid, Signal::SIGSTOP).unwrap();
+    while get_process_state(child.id()).unwrap() != 'T' {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let mut proc = Process::attach(&pid.to_string()).expect("attach failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.detach().expect("detach failed");
+    drop(proc);
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(tracer_pid(pid), 0);
+    assert_eq!(get_process_state(child.id()).unwrap(), 'T');
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_kill_reaps_process(){
+    let mut proc = launch_yes()
+        .expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let pid = proc.pid();
+    proc.kill().expect("kill failed");
+    assert_eq!(proc.process_state, ProcessState::Terminated);
+    assert!(!process_running(pid));
+    assert!(matches!(proc.kill(), Err(Error::InvalidState { state: ProcessState::Terminated, .. })));
+    assert!(matches!(proc.detach(), Err(Error::InvalidState { .. })));
+}
+
+fn spawn_named(name: &str) -> std::process::Child {
+    let child = std::process::Command::new("sleep").arg0(name).arg("30").spawn().unwrap();
+    // until the exec is through /proc still shows our own name
+    let cmdline = format!("/proc/{}/cmdline", child.id());
+    while !std::fs::read(&cmdline).unwrap().starts_with(name.as_bytes()) {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    child
+}
+
+#[test]
+fn test_attach_seizes_without_sigstop(){
+    let mut child = spawn_named("rdb_seize_test");
+    let pid = Pid::from_raw(child.id() as i32);
+    let mut proc = attach_by_name("rdb_seize_test", false, |_| panic!("only one candidate"))
+        .expect("attach failed");
+    assert_eq!(proc.pid(), pid);
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.state, ProcessState::Stopped);
+    assert_eq!(reason.info, StopInfo::Trap(TrapKind::Interrupt));
+    proc.detach().expect("detach failed");
+    std::thread::sleep(Duration::from_millis(50));
+    // nothing was queued for the program to notice
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).unwrap();
+    assert!(status.lines().any(|line| line == "SigPnd:\t0000000000000000"));
+    assert!(status.lines().any(|line| line == "ShdPnd:\t0000000000000000"));
+    assert_eq!(get_process_state(child.id()).unwrap(), 'S');
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_attach_by_name_asks_when_ambiguous(){
+    let mut first = spawn_named("rdb_ambiguous_test");
+    let mut second = spawn_named("rdb_ambiguous_test");
+    let found = find_processes("rdb_ambiguous_test");
+    let pids: Vec<u32> = found.iter().map(|entry| entry.pid.as_raw() as u32).collect();
+    assert_eq!(pids, [first.id(), second.id()]);
+    assert_eq!(found[0].cmdline, "rdb_ambiguous_test 30");
+
+    let proc = attach_by_name("rdb_ambiguous_test", false, |candidates| Some(candidates[1].pid))
+        .expect("attach failed");
+    assert_eq!(proc.pid().as_raw() as u32, second.id());
+    drop(proc);
+    let cancelled = attach_by_name("rdb_ambiguous_test", false, |_| None);
+    assert!(matches!(cancelled, Err(Error::Parse(_))));
+    let missing = attach_by_name("rdb_no_such_process", false, |_| None);
+    assert!(matches!(missing, Err(Error::ProcessNotFound(_))));
+    for child in [&mut first, &mut second] {
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
+
+#[test]
+fn test_wait_for_process_ignores_running_ones(){
+    let mut old = spawn_named("rdb_wait_test");
+    let starter = std::thread::spawn(|| {
+        std::thread::sleep(Duration::from_millis(200));
+        spawn_named("rdb_wait_test")
+    });
+    let found = wait_for_processes("rdb_wait_test", Duration::from_millis(10));
+    let mut new = starter.join().unwrap();
+    let pids: Vec<u32> = found.iter().map(|entry| entry.pid.as_raw() as u32).collect();
+    assert_eq!(pids, [new.id()]);
+    for child in [&mut old, &mut new] {
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
+
+#[test]
+fn test_attach_failures_are_diagnosed(){
+    // pid_max is at most 2^22, so this one cannot exist
+    assert_eq!(Process::attach("2147483647").err(), Some(Error::NoSuchProcess(i32::MAX)));
+
+    let mut traced = launch_yes().expect("Failed to launch process");
+    traced.wait_on_signal().expect("waitpid failed");
+    let attached = Process::attach(&traced.pid().to_string());
+    let Err(Error::AttachDenied { reason: AttachDenial::AlreadyTraced { tracer, .. }, .. }) = attached else {
+        panic!("expected AlreadyTraced, got {:?}", attached.err());
+    };
+    // the tracer is the thread that launched it
+    assert_eq!(tracer, nix::unistd::gettid().as_raw());
+
+    let mut zombie = std::process::Command::new("true").spawn().unwrap();
+    while get_process_state(zombie.id()).unwrap() != 'Z' {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    let attached = Process::attach(&zombie.id().to_string());
+    assert!(matches!(attached, Err(Error::AttachDenied { reason: AttachDenial::Zombie, .. })));
+    zombie.wait().unwrap();
+}
+
+/// States of all threads of `pid`, by tid.
+fn thread_states(pid: Pid) -> Vec<(u32, char)> {
+    let mut states: Vec<(u32, char)> = std::fs::read_dir(format!("/proc/{}/task", pid))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_str().unwrap().parse::<u32>().unwrap())
+        .filter_map(|tid| Some((tid, get_process_state(tid).ok()?)))
+        .collect();
+    states.sort();
+    states
+}
+
+#[test]
+fn test_threads_stop_together_at_breakpoint(){
+    let target = compile_target("threads");
+    let mut proc = Process::launch(&target).expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    let worker_entry = VirtAddr::new(proc.registers().read_by_id_as::<u64>(RegisterId::R12));
+    let id = proc.create_breakpoint_site(worker_entry, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+
+    let mut tids = Vec::new();
+    for _ in 0..3 {
+        proc.resume().expect("resume failed");
+        let reason = proc.wait_on_signal().expect("waitpid failed");
+        assert_eq!(reason.info, StopInfo::Trap(TrapKind::SoftwareBreakpoint(id)));
+        assert_eq!(proc.get_pc(), worker_entry);
+        let thread = proc.current_thread();
+        assert_ne!(thread.tid(), proc.pid());
+        tids.push(thread.tid());
+        // all-stop, no thread runs while we look at one
+        assert!(thread_states(proc.pid()).iter().all(|(_, state)| *state == 't'));
+        assert_eq!(proc.threads().len(), thread_states(proc.pid()).len());
+    }
+    tids.sort();
+    tids.dedup();
+    assert_eq!(tids.len(), 3);
+
+    proc.resume().expect("resume failed");
+    let reason = proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(reason.info, StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_attach_finds_all_threads(){
+    let target = compile_target("threads");
+    let mut child = std::process::Command::new(&target).arg("wait").spawn().unwrap();
+    let pid = Pid::from_raw(child.id() as i32);
+    while thread_states(pid).len() < 4 {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    let mut proc = Process::attach(&pid.to_string()).expect("attach failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    let tids: Vec<u32> = proc.threads().iter().map(|t| t.tid().as_raw() as u32).collect();
+    assert_eq!(tids.len(), 4);
+    assert_eq!(thread_states(pid).iter().map(|(tid, _)| *tid).collect::<Vec<_>>(), tids);
+    assert!(thread_states(pid).iter().all(|(_, state)| *state == 't'));
+
+    let leader_rsp = proc.registers().read_by_id_as::<u64>(RegisterId::Rsp);
+    proc.select_thread(2).unwrap();
+    assert_eq!(proc.current_thread().id(), 2);
+    assert_ne!(proc.registers().read_by_id_as::<u64>(RegisterId::Rsp), leader_rsp);
+    assert!(matches!(proc.select_thread(5), Err(Error::NoSuchThread(5))));
+
+    proc.detach().expect("detach failed");
+    // running again, the workers go back to sleeping in pause()
+    while !thread_states(pid).iter().all(|(_, state)| *state == 'S') {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+/// Launches a target that reports a function address in r12 on an int3, and sets a breakpoint
+/// on that function.
+fn launch_to_marker(options: &LaunchOptions) -> (Process, VirtAddr) {
+    let mut proc = Process::launch_with_options(options).expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    let address = VirtAddr::new(proc.registers().read_by_id_as::<u64>(RegisterId::R12));
+    let id = proc.create_breakpoint_site(address, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+    (proc, address)
+}
+
+fn continue_to_stop(proc: &mut Process) -> StopInfo {
+    proc.resume().expect("resume failed");
+    proc.wait_on_signal().expect("waitpid failed").info
+}
+
+#[test]
+fn test_fork_follows_parent_and_detaches_child(){
+    let target = compile_target("fork");
+    let (mut proc, after_fork) = launch_to_marker(&LaunchOptions::new(target));
+    let parent = proc.pid();
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::Fork));
+    assert_eq!(proc.pid(), parent);
+    assert!(proc.take_unfollowed().is_none());
+    // the child had the int3 removed before it was let go, it exits normally with 7
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::SoftwareBreakpoint(1)));
+    assert_eq!(proc.get_pc(), after_fork);
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::ExitCode(7));
+}
+
+#[test]
+fn test_fork_follows_child_and_keeps_parent(){
+    let target = compile_target("fork");
+    let (mut proc, after_fork) = launch_to_marker(&LaunchOptions::new(target));
+    proc.set_follow_fork_mode(FollowForkMode::Child);
+    proc.set_detach_on_fork(false);
+    let parent_pid = proc.pid();
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::Fork));
+    assert_ne!(proc.pid(), parent_pid);
+    let mut parent = proc.take_unfollowed().expect("parent was not kept");
+    assert_eq!(parent.pid(), parent_pid);
+    assert_eq!(parent.process_state, ProcessState::Stopped);
+    assert_eq!(get_process_state(parent_pid.as_raw() as u32), Ok('t'));
+
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::SoftwareBreakpoint(1)));
+    assert_eq!(proc.get_pc(), after_fork);
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::ExitCode(7));
+
+    assert_eq!(continue_to_stop(&mut parent), StopInfo::Trap(TrapKind::SoftwareBreakpoint(1)));
+    assert_eq!(continue_to_stop(&mut parent), StopInfo::ExitCode(7));
+}
+
+#[test]
+fn test_vfork_lifts_breakpoints_while_child_shares_memory(){
+    let target = compile_target("fork");
+    let (mut proc, after_fork) = launch_to_marker(&LaunchOptions::new(target).args(["vfork"]));
+    let parent = proc.pid();
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::Vfork));
+    assert_ne!(proc.read_memory(after_fork, 1).unwrap(), [0xcc]);
+    // back in place once the child is gone, the parent stops at its own call
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::SoftwareBreakpoint(1)));
+    assert_eq!(proc.pid(), parent);
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::ExitCode(7));
+}
+
+#[test]
+fn test_exec_rearms_breakpoints(){
+    let target = compile_target("exec");
+    let (mut proc, marker) = launch_to_marker(&LaunchOptions::new(target));
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::Exec));
+    assert!(proc.breakpoint_sites().get_by_id(1).unwrap().is_enabled());
+    assert_eq!(proc.read_memory(marker, 1).unwrap(), [0xcc]);
+    // the int3 of the new image, then the breakpoint in it
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::Unknown));
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::SoftwareBreakpoint(1)));
+    assert_eq!(proc.get_pc(), marker);
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_exec_of_another_program_leaves_breakpoints_disabled(){
+    let target = compile_target("exec");
+    let (mut proc, marker) = launch_to_marker(&LaunchOptions::new(target).args(["true"]));
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::Exec));
+    assert_eq!(proc.elf().unwrap().path(), std::fs::canonicalize("/bin/true").unwrap());
+    // the address is somewhere in true now, which must run untouched
+    assert!(!proc.breakpoint_sites().get_by_id(1).unwrap().is_enabled());
+    assert_ne!(proc.read_memory(marker, 1).ok(), Some(vec![0xcc]));
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_elf_of_launched_and_attached_process(){
+    let target = compile_target("stop_at");
+    let mut proc = Process::launch(&target).expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    let elf = proc.elf().expect("no executable loaded");
+    assert_eq!(elf.path(), Path::new(&target));
+    assert!(elf.function("main").is_some());
+
+    let mut child = spawn_named("rdb_elf_test");
+    let mut proc = Process::attach(&child.id().to_string()).expect("attach failed");
+    proc.wait_on_signal().expect("waitpid failed");
+    let sleep = std::fs::canonicalize(format!("/proc/{}/exe", child.id())).unwrap();
+    assert_eq!(proc.elf().expect("no executable loaded").path(), sleep);
+    proc.detach().expect("detach failed");
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_line_entry_at_stop(){
+    let target = compile_target("stop_at");
+    let mut proc = Process::launch(&target).expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::Unknown));
+    // the pc is past the int3, which is still on the line of the asm statement
+    let entry = proc.line_entry_at(proc.get_pc() - 1).expect("no line entry at the int3");
+    assert!(entry.file.ends_with("tests/targets/stop_at.c"), "{}", entry.file.display());
+    assert_eq!(entry.line, 4);
+    assert!(proc.line_entry_at(VirtAddr::new(0)).is_none());
+}
+
+/// Copies `target` to `name` and moves its debug info out to `name.debug`.
+fn split_debug_info(target: &str, name: &str) -> (String, String) {
+    let stripped = format!("{}/{}", env!("CARGO_TARGET_TMPDIR"), name);
+    let debug = format!("{}.debug", stripped);
+    std::fs::copy(target, &stripped).unwrap();
+    let _ = std::fs::remove_file(&debug);
+    for args in [
+        vec!["objcopy", "--only-keep-debug", &stripped, &debug],
+        vec!["strip", "--strip-debug", &stripped],
+        vec!["objcopy", &format!("--add-gnu-debuglink={}", debug), &stripped],
+    ] {
+        let status = std::process::Command::new(args[0]).args(&args[1..]).status().unwrap();
+        assert!(status.success(), "{} failed", args[0]);
+    }
+    (stripped, debug)
+}
+
+#[test]
+fn test_separate_debug_file_by_debuglink(){
+    let (stripped, _) = split_debug_info(&compile_target("stop_at"), "stop_at_debuglink");
+    let elf = Elf::open(&stripped).unwrap();
+    assert!(elf.section(".debug_line").is_none());
+    let main = elf.function("main").unwrap();
+    let entry = elf.line_table().expect("debug file not found").entry_at(main.value).unwrap();
+    assert!(entry.file.ends_with("tests/targets/stop_at.c"));
+    assert_eq!(entry.line, 3);
+}
+
+#[test]
+fn test_separate_debug_file_by_build_id(){
+    let (stripped, debug) = split_debug_info(&compile_target("stop_at"), "stop_at_build_id");
+    let elf = Elf::open(&stripped).unwrap();
+    let hex: String = elf.build_id().expect("no build id").iter().map(|b| format!("{:02x}", b)).collect();
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("debug_root");
+    let dir = root.join(".build-id").join(&hex[..2]);
+    std::fs::create_dir_all(&dir).unwrap();
+    // moved away so the debuglink finds nothing
+    std::fs::rename(&debug, dir.join(format!("{}.debug", &hex[2..]))).unwrap();
+    let debug_file = find_debug_file(&elf, &root).expect("debug file not found");
+    assert_eq!(debug_file.build_id(), elf.build_id());
+    assert!(find_debug_file(&elf, Path::new("/nonexistent")).is_none());
+}
+
+#[test]
+fn test_symbols_from_symtab_and_dynsym(){
+    // exported, main is in .dynsym as well as .symtab
+    let source = format!("{}/tests/targets/stop_at.c", env!("CARGO_MANIFEST_DIR"));
+    let exported = format!("{}/stop_at_exported", env!("CARGO_TARGET_TMPDIR"));
+    let status = std::process::Command::new("cc").args(["-g", "-O0", "-rdynamic", "-o", &exported, &source]).status().unwrap();
+    assert!(status.success(), "Failed to compile {}", source);
+    let elf = Elf::open(&exported).unwrap();
+    assert_eq!(elf.symbols_by_name("main").count(), 1);
+
+    // renamed in .symtab only, the .dynsym name still finds it
+    let renamed = format!("{}/stop_at_renamed", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::copy(&exported, &renamed).unwrap();
+    let status = std::process::Command::new("objcopy").args(["--redefine-sym", "main=renamed_main", &renamed]).status().unwrap();
+    assert!(status.success(), "objcopy failed");
+    let elf = Elf::open(&renamed).unwrap();
+    let main = elf.function("main").expect("main not found in .dynsym");
+    assert_eq!(elf.function("renamed_main").unwrap().value, main.value);
+}
+
+fn source_line(proc: &Process) -> u64 {
+    proc.source_location().expect("no source location").1
+}
+
+#[test]
+fn test_step_in_over_and_out(){
+    let target = compile_target("step");
+    let options = LaunchOptions::new(target).stop_at(StopAt::Main).stdout(Stdio::Null);
+    let mut proc = Process::launch_with_options(&options).unwrap();
+    proc.wait_on_signal().expect("waitpid failed");
+    let done = StopInfo::Trap(TrapKind::SingleStep);
+
+    assert_eq!(proc.step_over().unwrap().info, done);
+    assert_eq!(source_line(&proc), 20);
+    // into factorial and past its prologue
+    assert_eq!(proc.step_in().unwrap().info, done);
+    assert_eq!(proc.function_frames()[0].name, "factorial");
+    assert_eq!(source_line(&proc), 10);
+    assert_eq!(proc.step_out().unwrap().info, done);
+    assert_eq!(proc.registers().read_by_id_as::<u64>(RegisterId::Rax), 24);
+    assert_eq!(proc.function_frames()[0].name, "main");
+
+    // the inlined call of twice counts as line 21 until stepped into
+    assert_eq!(proc.step_over().unwrap().info, done);
+    assert_eq!((source_line(&proc), proc.inline_height()), (21, 1));
+    let pc = proc.get_pc();
+    assert_eq!(proc.step_in().unwrap().info, done);
+    assert_eq!(proc.get_pc(), pc);
+    assert_eq!(proc.function_frames()[0].name, "twice");
+    assert!(proc.function_frames()[0].is_inlined());
+    assert_eq!(source_line(&proc), 6);
+    assert_eq!(proc.step_out().unwrap().info, done);
+    assert_eq!(proc.function_frames()[0].name, "main");
+
+    // puts has no line information, stepping runs through it
+    assert_eq!(proc.step_over().unwrap().info, done);
+    assert_eq!(source_line(&proc), 22);
+    assert_eq!(proc.step_in().unwrap().info, done);
+    assert_eq!(source_line(&proc), 23);
+    assert_eq!(proc.step_in().unwrap().info, done);
+    assert_eq!(proc.function_frames()[0].name, "half");
+    assert_eq!(proc.step_out().unwrap().info, done);
+    assert_eq!(proc.registers().read_by_id_as::<f64>(RegisterId::Xmm0), 24.0);
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_step_out_of_recursion(){
+    let target = compile_target("step");
+    let options = LaunchOptions::new(target).stop_at(StopAt::Main).stdout(Stdio::Null);
+    let mut proc = Process::launch_with_options(&options).unwrap();
+    proc.wait_on_signal().expect("waitpid failed");
+    proc.step_over().unwrap();
+    let factorial = proc.lookup_symbol("factorial").unwrap();
+    let id = proc.create_breakpoint_site(factorial, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+    // a next over the call stops at a breakpoint inside it
+    assert_eq!(proc.step_over().unwrap().info, StopInfo::Trap(TrapKind::SoftwareBreakpoint(id)));
+    for _ in 0..2 {
+        assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::SoftwareBreakpoint(id)));
+    }
+    proc.disable_breakpoint_site(id).unwrap();
+    // factorial(2) returns into factorial(3), the deeper calls do not end the finish early
+    assert_eq!(proc.step_out().unwrap().info, StopInfo::Trap(TrapKind::SingleStep));
+    assert_eq!(proc.registers().read_by_id_as::<u64>(RegisterId::Rax), 2);
+    assert_eq!(source_line(&proc), 12);
+    assert_eq!(proc.step_out().unwrap().info, StopInfo::Trap(TrapKind::SingleStep));
+    assert_eq!(proc.registers().read_by_id_as::<u64>(RegisterId::Rax), 6);
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_step_out_without_frame_pointer(){
+    let target = compile_target_with("step_optimized", &["-O2", "-fomit-frame-pointer"]);
+    let options = LaunchOptions::new(target).stop_at(StopAt::Main).stdout(Stdio::Null);
+    let mut proc = Process::launch_with_options(&options).unwrap();
+    proc.wait_on_signal().expect("waitpid failed");
+    let mix = proc.lookup_symbol("mix").unwrap();
+    let id = proc.create_breakpoint_site(mix, false).unwrap();
+    proc.enable_breakpoint_site(id).unwrap();
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::Trap(TrapKind::SoftwareBreakpoint(id)));
+    proc.disable_breakpoint_site(id).unwrap();
+    // work pushes rbp as a plain callee-saved register, only the call frame information
+    // tells where its return address is
+    assert_eq!(proc.step_out().unwrap().info, StopInfo::Trap(TrapKind::SingleStep));
+    assert_eq!(proc.registers().read_by_id_as::<u64>(RegisterId::Rax), 33);
+    assert_eq!(proc.step_out().unwrap().info, StopInfo::Trap(TrapKind::SingleStep));
+    assert_eq!(proc.registers().read_by_id_as::<u64>(RegisterId::Rax), 39013);
+    assert_eq!(continue_to_stop(&mut proc), StopInfo::ExitCode(0));
+}
+
+#[test]
+fn test_step_out_without_call_frame_information(){
+    let mut proc = Process::launch("true").expect("Failed to launch process");
+    proc.wait_on_signal().expect("waitpid failed");
+    // stopped in the dynamic loader, whose frames the program knows nothing about
+    let pc = proc.get_pc();
+    assert_eq!(proc.step_out().map(|_| ()), Err(Error::NoFrameInfo(pc)));
+    assert_eq!(proc.get_pc(), pc);
+}
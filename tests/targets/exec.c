@@ -0,0 +1,22 @@
+// Execs itself once, both images hand the debugger the address of marker in r12 on an int3.
+// Only the second image calls marker. Given `true` it execs /bin/true instead.
+#include <string.h>
+#include <unistd.h>
+
+__attribute__((noinline)) void marker(void) {
+    __asm__ volatile("");
+}
+
+int main(int argc, char **argv) {
+    __asm__ volatile("lea marker(%%rip), %%r12\n\tint3" : : : "r12");
+    if (argc > 1 && strcmp(argv[1], "true") == 0) {
+        execl("/bin/true", "true", (char *)NULL);
+        return 1;
+    }
+    if (argc == 1) {
+        execl("/proc/self/exe", argv[0], "again", (char *)NULL);
+        return 1;
+    }
+    marker();
+    return 0;
+}
@@ -0,0 +1,24 @@
+// Forks, or vforks when given "vfork", and both sides call after_fork, whose address main hands
+// the debugger in r12 on an int3 first. The parent exits with the child's exit status, which is
+// only 7 if the child got through after_fork without tripping over a breakpoint.
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+__attribute__((noinline)) void after_fork(void) {
+    __asm__ volatile("");
+}
+
+int main(int argc, char **argv) {
+    __asm__ volatile("lea after_fork(%%rip), %%r12\n\tint3" : : : "r12");
+    int use_vfork = argc > 1 && strcmp(argv[1], "vfork") == 0;
+    pid_t child = use_vfork ? vfork() : fork();
+    if (child == 0) {
+        after_fork();
+        _exit(7);
+    }
+    int status;
+    waitpid(child, &status, 0);
+    after_fork();
+    return WIFEXITED(status) ? WEXITSTATUS(status) : 100 + WTERMSIG(status);
+}
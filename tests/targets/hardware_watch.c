@@ -0,0 +1,10 @@
+// Hands the address of `watched` to the debugger in r12, stops on an int3 and then writes
+// to the variable twice so watchpoints have something to catch.
+volatile unsigned long watched = 0;
+
+int main() {
+    __asm__ volatile("mov %0, %%r12\n\tint3" : : "r"(&watched) : "r12");
+    watched = 42;
+    watched = 43;
+    return 0;
+}
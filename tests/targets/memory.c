@@ -0,0 +1,21 @@
+// Maps two readable pages followed by an unmapped one, fills them with i & 0xff and hands the
+// address to the debugger in r12 before stopping on an int3. Given an argument it then unmaps
+// the second page as well and stops on another int3.
+#include <sys/mman.h>
+#include <unistd.h>
+
+int main(int argc, char **argv) {
+    (void)argv;
+    long page = sysconf(_SC_PAGESIZE);
+    unsigned char *buffer = mmap(0, 3 * page, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    munmap(buffer + 2 * page, page);
+    for (long i = 0; i < 2 * page; i++) {
+        buffer[i] = i & 0xff;
+    }
+    __asm__ volatile("mov %0, %%r12\n\tint3" : : "r"(buffer) : "r12");
+    if (argc > 1) {
+        munmap(buffer + page, page);
+        __asm__ volatile("int3");
+    }
+    return 0;
+}
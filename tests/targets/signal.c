@@ -0,0 +1,20 @@
+// Raises SIGUSR1, whose handler counts it, and exits with the count. Given an argument it
+// writes through a null pointer afterwards and dies of SIGSEGV.
+#include <signal.h>
+
+static volatile sig_atomic_t received;
+
+void on_usr1(int signal) {
+    (void)signal;
+    received++;
+}
+
+int main(int argc, char **argv) {
+    (void)argv;
+    signal(SIGUSR1, on_usr1);
+    raise(SIGUSR1);
+    if (argc > 1) {
+        *(volatile int *)0 = 0;
+    }
+    return received;
+}
@@ -0,0 +1,25 @@
+// Source stepping: a recursive call, an inlined function, a call into libc, which has no line
+// information, and a function returning a double. Exits with 0.
+#include <stdio.h>
+
+static inline __attribute__((always_inline)) int twice(int x) {
+    return x * 2;
+}
+
+int factorial(int n) {
+    if (n <= 1)
+        return 1;
+    return n * factorial(n - 1);
+}
+
+double half(double x) {
+    return x / 2;
+}
+
+int main() {
+    int a = factorial(4);
+    int b = twice(a);
+    puts("stepping");
+    double c = half(b);
+    return (int)c - 24;
+}
@@ -0,0 +1,20 @@
+// Built with -O2 -fomit-frame-pointer: work keeps values alive across calls of mix in
+// callee-saved registers, rbp among them, and has no frame pointer to find its return address
+// with.
+__attribute__((noipa)) long mix(long a, long b) {
+    return a * 31 + b;
+}
+
+__attribute__((noipa)) long work(long a, long b, long c, long d) {
+    long x = mix(a, b);
+    long y = mix(c, d);
+    long z = mix(x, a);
+    long w = mix(y, b);
+    return mix(z + c, w + d) + x + y + z + w;
+}
+
+int main(int argc, char **argv) {
+    (void)argv;
+    long result = work(argc, 2, 3, 4);
+    return result == 0;
+}
@@ -0,0 +1,6 @@
+// Hands the debugger the runtime address of main in r12 before stopping on an int3, so a stop at
+// main can be checked without reading the symbol table.
+int main() {
+    __asm__ volatile("lea main(%%rip), %%r12\n\tint3" : : : "r12");
+    return 0;
+}
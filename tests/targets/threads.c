@@ -0,0 +1,34 @@
+// Three workers call worker_entry, main hands the debugger its address in r12 before stopping on
+// an int3. Given an argument there is no int3 and the workers never return, leaving a
+// multithreaded process to attach to.
+#include <pthread.h>
+#include <unistd.h>
+
+static int wait_forever;
+
+__attribute__((noinline)) void worker_entry(void) {
+    while (wait_forever) {
+        pause();
+    }
+}
+
+static void *worker(void *arg) {
+    worker_entry();
+    return arg;
+}
+
+int main(int argc, char **argv) {
+    (void)argv;
+    wait_forever = argc > 1;
+    if (!wait_forever) {
+        __asm__ volatile("lea worker_entry(%%rip), %%r12\n\tint3" : : : "r12");
+    }
+    pthread_t threads[3];
+    for (int i = 0; i < 3; i++) {
+        pthread_create(&threads[i], NULL, worker, NULL);
+    }
+    for (int i = 0; i < 3; i++) {
+        pthread_join(threads[i], NULL);
+    }
+    return 0;
+}